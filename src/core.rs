@@ -1,4 +1,6 @@
-use std::io;
+use crate::http::{HttpConfig, HttpFileSystemBuilder, UrlResolver};
+use std::io::{self, SeekFrom};
+use std::sync::{Arc, RwLock};
 use thiserror::Error;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -20,8 +22,17 @@ pub enum FsError {
     #[error("Unsupported protocol: {0}")]
     UnsupportedProtocol(String),
 
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("HTTP error: {status}")]
+    Http { status: u16 },
+
     #[error("Rate limited: retry after {retry_after_secs:?}s")]
     RateLimited { retry_after_secs: Option<u64> },
+
+    #[error("Operation timed out")]
+    Timeout,
 }
 
 impl From<io::Error> for FsError {
@@ -44,6 +55,14 @@ pub trait File: Send + Sync {
     /// Seek to absolute position.
     fn seek(&mut self, pos: u64) -> Result<()>;
 
+    /// Seek relative to the start, current position, or end of the file and
+    /// return the new absolute position.  `SeekFrom::End` needs a known size.
+    fn seek_from(&mut self, pos: SeekFrom) -> Result<u64> {
+        let new_pos = crate::resolve_seek(pos, self.tell(), || self.size())?;
+        self.seek(new_pos)?;
+        Ok(new_pos)
+    }
+
     /// Get current position.
     fn tell(&self) -> u64;
 
@@ -55,30 +74,284 @@ pub trait File: Send + Sync {
         None
     }
 
+    /// Bytes left between the current position and EOF, if the size is
+    /// known.  Like `size`, the first call may probe the size.
+    fn remaining(&self) -> Option<u64> {
+        self.size().map(|size| size.saturating_sub(self.tell()))
+    }
+
+    /// Write `buf` at the current position and return the number of bytes
+    /// written.  Only files opened with [`OpenMode::Write`] support this.
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let _ = buf;
+        Err(FsError::Io("File is not open for writing".into()))
+    }
+
+    /// Push any buffered writes to the underlying store.  A no-op for files
+    /// opened for reading.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Close the file (optional, called automatically on drop).
     fn close(&mut self) {}
+
+    /// The URL this file reads from or writes to, for logging and error
+    /// messages.  `None` for files without one.
+    fn url(&self) -> Option<&str> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpenMode {
     Read,
+    /// Create or replace the file.  Writes are buffered and uploaded as a
+    /// whole on `flush` or `close`.
+    Write,
 }
 
 pub trait FileSystem: Send + Sync {
     fn open(&self, path: &str, mode: OpenMode) -> Result<Box<dyn File + Send + Sync>>;
 }
 
+/// Builds a filesystem for a URL of a registered scheme.
+pub type SchemeFactory = Arc<dyn Fn(&str) -> Result<Box<dyn FileSystem>> + Send + Sync>;
+
+const BUILTIN_SCHEMES: &[&str] = &["http", "https"];
+
+static SCHEMES: RwLock<Vec<(String, SchemeFactory)>> = RwLock::new(Vec::new());
+
+static DEFAULT_CONFIG: RwLock<Option<HttpConfig>> = RwLock::new(None);
+
+/// Set the configuration [`create`] and [`open`] use for `http`/`https`
+/// URLs (including the C API's `pravaha_create` and `pravaha_open_url`).
+/// May be called from any thread, any number of times; it only affects
+/// filesystems created afterwards.  Filesystems built with
+/// [`HttpFileSystem::builder`] do not consult it.
+pub fn set_default_config(config: HttpConfig) {
+    let mut default = DEFAULT_CONFIG.write().unwrap_or_else(|e| e.into_inner());
+    *default = Some(config);
+}
+
+fn default_builder() -> HttpFileSystemBuilder {
+    let config = DEFAULT_CONFIG
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    match config {
+        Some(config) => HttpFileSystemBuilder::from_config(config),
+        None => HttpFileSystemBuilder::new(),
+    }
+}
+
+/// Register a filesystem factory for `scheme` (e.g. `"s3"`), used by
+/// [`create`] and [`open`].  Scheme names are case-insensitive.  Registering
+/// a scheme again replaces the previous factory; registering `http` or
+/// `https` overrides the built-in HTTP filesystem.
+pub fn register_scheme(scheme: &str, factory: SchemeFactory) {
+    let scheme = scheme.to_ascii_lowercase();
+    let mut schemes = SCHEMES.write().unwrap_or_else(|e| e.into_inner());
+    schemes.retain(|(s, _)| *s != scheme);
+    schemes.push((scheme, factory));
+}
+
+/// Register `scheme` as one whose URLs name HTTP resources indirectly, such
+/// as opaque handles issued by a gateway: [`create`] and [`open`] serve it
+/// with an HTTP filesystem on the default configuration that maps each
+/// request's URL through `resolver`.  See
+/// [`HttpFileSystemBuilder::url_resolver`] to use a resolver with a
+/// filesystem of your own.
+pub fn register_resolver(scheme: &str, resolver: Arc<dyn UrlResolver>) {
+    let owned = scheme.to_string();
+    register_scheme(
+        scheme,
+        Arc::new(move |_| {
+            let fs = default_builder()
+                .url_resolver(&owned, Arc::clone(&resolver))
+                .build();
+            Ok(Box::new(fs))
+        }),
+    );
+}
+
+/// Stop the background work of every HTTP filesystem in the process, for a
+/// clean exit: prefetches and `open_stream` transfers are cancelled, and
+/// this returns once the async tasks running them have finished and curl's
+/// `prefetch_multi` thread has been joined.  It does not wait for the
+/// transfers on curl's blocking threads, which are aborted at their next
+/// progress check, possibly after this returns.  Nor does it interrupt a
+/// call blocked in a read or write, such as the parts of `read_all` or an
+/// upload.  The filesystems stay valid but unusable: every request they
+/// would start fails with an `Io` error.  Each filesystem's worker threads
+/// exit when it is dropped, which no longer waits on a download.
+pub fn shutdown() {
+    crate::http::shut_down_all();
+}
+
+/// Schemes [`create`] currently accepts: the built-in `http`/`https` followed
+/// by any registered with [`register_scheme`], in registration order.
+pub fn supported_schemes() -> Vec<String> {
+    let mut out: Vec<String> = BUILTIN_SCHEMES.iter().map(|s| s.to_string()).collect();
+    let schemes = SCHEMES.read().unwrap_or_else(|e| e.into_inner());
+    for (scheme, _) in schemes.iter() {
+        if !out.contains(scheme) {
+            out.push(scheme.clone());
+        }
+    }
+    out
+}
+
+fn registered_factory(scheme: &str) -> Option<SchemeFactory> {
+    let schemes = SCHEMES.read().unwrap_or_else(|e| e.into_inner());
+    schemes
+        .iter()
+        .find(|(s, _)| s.eq_ignore_ascii_case(scheme))
+        .map(|(_, f)| Arc::clone(f))
+}
+
 /// Create a filesystem for the given URL.
 pub fn create(url: &str) -> Result<Box<dyn FileSystem>> {
-    if url.starts_with("http://") || url.starts_with("https://") {
-        Ok(Box::new(crate::http::HttpFileSystem::new()))
-    } else {
-        Err(FsError::UnsupportedProtocol(url.to_string()))
+    let scheme = crate::url::scheme(url);
+    if let Some(factory) = scheme.and_then(registered_factory) {
+        return factory(url);
+    }
+    match scheme {
+        Some(s) if BUILTIN_SCHEMES.iter().any(|b| b.eq_ignore_ascii_case(s)) => {
+            Ok(Box::new(default_builder().build()))
+        }
+        _ => Err(FsError::UnsupportedProtocol(unsupported_message(
+            url, scheme,
+        ))),
+    }
+}
+
+/// Describe why `url` was rejected, listing what is supported and pointing
+/// out likely typos (`htttp`) or local paths, which are not opened at all.
+fn unsupported_message(url: &str, scheme: Option<&str>) -> String {
+    let supported = supported_schemes();
+    let list = supported.join(", ");
+    match scheme {
+        Some(scheme) => {
+            let lower = scheme.to_ascii_lowercase();
+            let suggestion = supported
+                .iter()
+                .find(|s| edit_distance(&lower, s) == 1)
+                .map(|s| format!(" (did you mean '{s}'?)"))
+                .unwrap_or_default();
+            format!("scheme '{scheme}' not supported; supported: {list}{suggestion}")
+        }
+        None => {
+            let hint = if url.starts_with(['/', '.', '~']) || url.contains('\\') {
+                " (this looks like a local path; only URLs can be opened)"
+            } else {
+                ""
+            };
+            format!("'{url}' has no URL scheme; supported: {list}{hint}")
+        }
     }
 }
 
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
 /// Open a file directly.
 pub fn open(url: &str, mode: OpenMode) -> Result<Box<dyn File + Send + Sync>> {
     let fs = create(url)?;
-    fs.open(url, mode)
+    let file = fs.open(url, mode)?;
+    Ok(Box::new(OwnedFile { file, _fs: fs }))
+}
+
+/// A file that keeps the filesystem it was opened from alive, since files
+/// rely on their filesystem's runtime.  Fields drop in order, file first.
+struct OwnedFile {
+    file: Box<dyn File + Send + Sync>,
+    _fs: Box<dyn FileSystem>,
+}
+
+impl File for OwnedFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.file.read(buf)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        self.file.read_at(offset, buf)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<()> {
+        self.file.seek(pos)
+    }
+
+    fn seek_from(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.file.seek_from(pos)
+    }
+
+    fn tell(&self) -> u64 {
+        self.file.tell()
+    }
+
+    fn eof(&self) -> bool {
+        self.file.eof()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.file.size()
+    }
+
+    fn remaining(&self) -> Option<u64> {
+        self.file.remaining()
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+
+    fn close(&mut self) {
+        self.file.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(url: &str) -> String {
+        match create(url) {
+            Err(FsError::UnsupportedProtocol(msg)) => msg,
+            other => panic!("{url}: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unsupported_urls_name_the_problem() {
+        let ftp = message("ftp://host/file");
+        assert!(ftp.starts_with("scheme 'ftp' not supported; supported: "));
+        assert!(ftp.contains("https") && !ftp.contains("did you mean"));
+
+        assert!(message("htttp://host/file").ends_with("(did you mean 'http'?)"));
+
+        for path in ["/data/file.bin", "./file.bin", "C:\\data\\file.bin"] {
+            let msg = message(path);
+            assert!(msg.contains("no URL scheme"), "{msg}");
+            assert!(
+                msg.contains("local path") && !msg.contains("file://"),
+                "{msg}"
+            );
+        }
+    }
 }
@@ -19,6 +19,8 @@ pub enum PravahaErrorCode {
     PRAVAHA_INVALID_ARGUMENT = 6,
     PRAVAHA_RATE_LIMITED = 7,
     PRAVAHA_PANIC = 8,
+    PRAVAHA_HTTP_STATUS = 9,
+    PRAVAHA_TIMEOUT = 10,
     PRAVAHA_UNKNOWN = 99,
 }
 
@@ -30,7 +32,10 @@ impl From<&FsError> for PravahaErrorCode {
             FsError::Io(_) => PravahaErrorCode::PRAVAHA_IO,
             FsError::FileClosed => PravahaErrorCode::PRAVAHA_FILE_CLOSED,
             FsError::UnsupportedProtocol(_) => PravahaErrorCode::PRAVAHA_UNSUPPORTED_PROTOCOL,
+            FsError::InvalidUrl(_) => PravahaErrorCode::PRAVAHA_INVALID_ARGUMENT,
             FsError::RateLimited { .. } => PravahaErrorCode::PRAVAHA_RATE_LIMITED,
+            FsError::Http { .. } => PravahaErrorCode::PRAVAHA_HTTP_STATUS,
+            FsError::Timeout => PravahaErrorCode::PRAVAHA_TIMEOUT,
         }
     }
 }
@@ -169,6 +174,14 @@ pub unsafe extern "C" fn pravaha_filesystem_free(fs: *mut PravahaFilesystem) {
     }
 }
 
+fn parse_mode(mode: &str) -> Option<crate::OpenMode> {
+    match mode {
+        "r" | "rb" => Some(crate::OpenMode::Read),
+        "w" | "wb" => Some(crate::OpenMode::Write),
+        _ => None,
+    }
+}
+
 /// Open a file via an existing filesystem handle.
 /// Returns NULL on error; call `pravaha_last_error()` for details.
 ///
@@ -208,11 +221,12 @@ pub unsafe extern "C" fn pravaha_open(
 
     let mode_owned = mode_str.to_owned();
 
-    let open_mode = if mode_owned == "r" || mode_owned == "rb" {
-        crate::OpenMode::Read
-    } else {
-        set_last_error_str("Only read mode ('r' or 'rb') is supported");
-        return ptr::null_mut();
+    let open_mode = match parse_mode(&mode_owned) {
+        Some(m) => m,
+        None => {
+            set_last_error_str("Mode must be 'r', 'rb', 'w' or 'wb'");
+            return ptr::null_mut();
+        }
     };
 
     ffi_catch(ptr::null_mut(), move || {
@@ -262,11 +276,12 @@ pub unsafe extern "C" fn pravaha_open_url(
     let url_owned = url_str.to_owned();
     let mode_owned = mode_str.to_owned();
 
-    let open_mode = if mode_owned == "r" || mode_owned == "rb" {
-        crate::OpenMode::Read
-    } else {
-        set_last_error_str("Only read mode ('r' or 'rb') is supported");
-        return ptr::null_mut();
+    let open_mode = match parse_mode(&mode_owned) {
+        Some(m) => m,
+        None => {
+            set_last_error_str("Mode must be 'r', 'rb', 'w' or 'wb'");
+            return ptr::null_mut();
+        }
     };
 
     ffi_catch(ptr::null_mut(), move || {
@@ -288,7 +303,11 @@ pub unsafe extern "C" fn pravaha_open_url(
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn pravaha_file_close(file: *mut PravahaFile) {
     if !file.is_null() {
-        ffi_catch((), move || drop(unsafe { Box::from_raw(file) }));
+        ffi_catch((), move || {
+            // Write files only upload on an explicit close, not on drop.
+            let mut file = unsafe { Box::from_raw(file) };
+            file.inner.close();
+        });
     }
 }
 
@@ -323,6 +342,65 @@ pub unsafe extern "C" fn pravaha_read(
     })
 }
 
+/// Write `size` bytes from `buffer` to a file opened with mode "w".
+/// Returns bytes written, or -1 on error.  Data is buffered and uploaded on
+/// `pravaha_flush()` or `pravaha_file_close()`.
+///
+/// # Safety
+/// - `file` must be a valid file handle.
+/// - `buffer` must be valid for reads of at least `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pravaha_write(
+    file: *mut PravahaFile,
+    buffer: *const c_void,
+    size: size_t,
+) -> ssize_t {
+    clear_last_error();
+
+    if file.is_null() || buffer.is_null() {
+        set_last_error_str("Null pointer argument");
+        return -1;
+    }
+
+    // caller guarantees buffer is valid for `size` bytes.
+    let buf = unsafe { slice::from_raw_parts(buffer as *const u8, size) };
+
+    ffi_catch(-1, move || match unsafe { &mut *file }.inner.write(buf) {
+        Ok(n) => n as ssize_t,
+        Err(e) => {
+            set_last_error(&e);
+            -1
+        }
+    })
+}
+
+/// Upload everything written so far.  Returns `PRAVAHA_SUCCESS` or an error
+/// code.  Close does the same, but cannot report a failed upload.
+///
+/// # Safety
+/// - `file` must be a valid file handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pravaha_flush(file: *mut PravahaFile) -> c_int {
+    clear_last_error();
+
+    if file.is_null() {
+        set_last_error_str("Null file pointer");
+        return PravahaErrorCode::PRAVAHA_INVALID_ARGUMENT as c_int;
+    }
+
+    ffi_catch(
+        PravahaErrorCode::PRAVAHA_PANIC as c_int,
+        move || match unsafe { &mut *file }.inner.flush() {
+            Ok(()) => PravahaErrorCode::PRAVAHA_SUCCESS as c_int,
+            Err(e) => {
+                let c = PravahaErrorCode::from(&e) as c_int;
+                set_last_error(&e);
+                c
+            }
+        },
+    )
+}
+
 /// Seek to an absolute byte position.
 /// Returns `PRAVAHA_SUCCESS` (0) on success, or an error code on failure.
 ///
@@ -407,6 +485,43 @@ pub unsafe extern "C" fn pravaha_size(
     })
 }
 
+/// Get the number of bytes between the cursor and EOF.
+/// Sets `*has_value` to 1 and writes the count into `*out_remaining` when
+/// the file size is known, or sets `*has_value` to 0 otherwise, including
+/// when the size probe fails.
+/// Returns `PRAVAHA_SUCCESS`, or `PRAVAHA_INVALID_ARGUMENT` on a NULL pointer.
+///
+/// # Safety
+/// - `file` must be a valid file handle.
+/// - `out_remaining` and `has_value` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pravaha_remaining(
+    file: *const PravahaFile,
+    out_remaining: *mut u64,
+    has_value: *mut c_int,
+) -> c_int {
+    clear_last_error();
+
+    if file.is_null() || out_remaining.is_null() || has_value.is_null() {
+        set_last_error_str("Null pointer argument");
+        return PravahaErrorCode::PRAVAHA_INVALID_ARGUMENT as c_int;
+    }
+
+    ffi_catch(PravahaErrorCode::PRAVAHA_PANIC as c_int, move || {
+        match unsafe { &*file }.inner.remaining() {
+            Some(n) => unsafe {
+                *out_remaining = n;
+                *has_value = 1
+            },
+            None => unsafe {
+                *out_remaining = 0;
+                *has_value = 0
+            },
+        }
+        PravahaErrorCode::PRAVAHA_SUCCESS as c_int
+    })
+}
+
 /// Check if the file position is at EOF.
 /// Returns 1 if EOF, 0 otherwise.
 /// Sets the last error if `file` is NULL.
@@ -425,6 +540,15 @@ pub unsafe extern "C" fn pravaha_eof(file: *const PravahaFile) -> c_int {
     ffi_catch(0, move || if unsafe { &*file }.inner.eof() { 1 } else { 0 })
 }
 
+/// Cancel the background work of every filesystem and wait for the tasks
+/// running it, e.g. before the process exits.  Handles stay valid but every
+/// further request fails; free them as usual.  See `pravaha::shutdown`.
+#[unsafe(no_mangle)]
+pub extern "C" fn pravaha_shutdown() {
+    clear_last_error();
+    ffi_catch((), crate::shutdown);
+}
+
 /// Returns a pointer to a static null-terminated version string.
 #[unsafe(no_mangle)]
 pub extern "C" fn pravaha_version() -> *const c_char {
@@ -0,0 +1,80 @@
+use std::io::Write;
+
+use flate2::write::MultiGzDecoder;
+
+use crate::core::{FsError, Result};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Decodes a streamed body if it turns out to be gzip and passes it through
+/// unchanged otherwise, decided by its first two bytes.  Concatenated gzip
+/// members, as left by appending to a `.gz` log, decode as one stream.
+pub(crate) struct Gunzip {
+    state: State,
+}
+
+enum State {
+    /// Fewer bytes than the magic number have arrived so far.
+    Sniffing(Vec<u8>),
+    Plain,
+    Gzip(MultiGzDecoder<Vec<u8>>),
+}
+
+impl Gunzip {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: State::Sniffing(Vec::new()),
+        }
+    }
+
+    /// Whether the body is being decoded.
+    pub(crate) fn is_gzip(&self) -> bool {
+        matches!(self.state, State::Gzip(_))
+    }
+
+    /// Feed the next piece of the body and return the bytes it yields,
+    /// possibly none.
+    pub(crate) fn push(&mut self, piece: Vec<u8>) -> Result<Vec<u8>> {
+        if let State::Sniffing(head) = &mut self.state {
+            head.extend_from_slice(&piece);
+            if head.len() < GZIP_MAGIC.len() && GZIP_MAGIC.starts_with(head) {
+                return Ok(Vec::new());
+            }
+            let head = std::mem::take(head);
+            self.state = if head.starts_with(&GZIP_MAGIC) {
+                State::Gzip(MultiGzDecoder::new(Vec::new()))
+            } else {
+                State::Plain
+            };
+            return self.push(head);
+        }
+        match &mut self.state {
+            State::Gzip(decoder) => {
+                decoder.write_all(&piece).map_err(corrupt)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            _ => Ok(piece),
+        }
+    }
+
+    /// The end of the body: the bytes still held back, or an error if the
+    /// gzip stream was cut short.
+    pub(crate) fn finish(&mut self) -> Result<Vec<u8>> {
+        match &mut self.state {
+            State::Sniffing(head) => {
+                let head = std::mem::take(head);
+                self.state = State::Plain;
+                Ok(head)
+            }
+            State::Plain => Ok(Vec::new()),
+            State::Gzip(decoder) => {
+                decoder.try_finish().map_err(corrupt)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+}
+
+fn corrupt(e: std::io::Error) -> FsError {
+    FsError::Protocol(format!("Invalid gzip body: {e}"))
+}
@@ -0,0 +1,223 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::{File, FileSystem, FsError, OpenMode, Result};
+use crate::http::HttpFileSystem;
+
+/// Serves files from a local mirror directory when they are present there,
+/// and from an [`HttpFileSystem`] otherwise.
+///
+/// A URL maps to the file at its path below the local root, ignoring the
+/// scheme, host and query: with a root of `/srv/mirror`,
+/// `https://cdn.example.com/data/a.bin` is looked up as
+/// `/srv/mirror/data/a.bin`.  The check happens on every `open`, so files
+/// added to the mirror later are picked up.  Writes always go to the remote.
+pub struct LayeredFileSystem {
+    root: PathBuf,
+    remote: HttpFileSystem,
+}
+
+impl LayeredFileSystem {
+    /// `local_root` is a directory path or a `file://` URL.
+    pub fn new(local_root: &str, remote: HttpFileSystem) -> Self {
+        let root = local_root.strip_prefix("file://").unwrap_or(local_root);
+        Self {
+            root: PathBuf::from(root),
+            remote,
+        }
+    }
+
+    /// The filesystem used for files missing from the local mirror.
+    pub fn remote(&self) -> &HttpFileSystem {
+        &self.remote
+    }
+
+    /// Where `url` would be found in the local mirror.  `None` if its path
+    /// could escape the root (a `..` segment).
+    pub fn local_path(&self, url: &str) -> Option<PathBuf> {
+        let path = crate::url::path(url);
+        let relative = Path::new(path.trim_start_matches('/'));
+        relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            .then(|| self.root.join(relative))
+    }
+}
+
+impl FileSystem for LayeredFileSystem {
+    fn open(&self, path: &str, mode: OpenMode) -> Result<Box<dyn File + Send + Sync>> {
+        if mode == OpenMode::Read
+            && let Some(local) = self.local_path(path)
+            && local.is_file()
+        {
+            return Ok(Box::new(LocalFile::open(&local, path)?));
+        }
+        self.remote.open(path, mode)
+    }
+}
+
+/// A file read from the local mirror.
+struct LocalFile {
+    file: std::fs::File,
+    /// The URL this file stands in for.
+    url: String,
+    size: u64,
+    pos: u64,
+    eof: bool,
+    closed: bool,
+    /// Held across the seek and read of a positional read on targets
+    /// without a call that does both.
+    #[cfg(not(any(unix, windows)))]
+    cursor: std::sync::Mutex<()>,
+}
+
+impl LocalFile {
+    fn open(path: &Path, url: &str) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            file,
+            url: url.to_string(),
+            size,
+            pos: 0,
+            eof: false,
+            closed: false,
+            #[cfg(not(any(unix, windows)))]
+            cursor: std::sync::Mutex::new(()),
+        })
+    }
+
+    #[cfg(unix)]
+    fn pread(&self, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(&self.file, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn pread(&self, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(&self.file, buf, offset)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn pread(&self, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+        let _cursor = self.cursor.lock().unwrap_or_else(|e| e.into_inner());
+        seek_read(&self.file, buf, offset)
+    }
+}
+
+/// Positional read through the file cursor, for targets without `pread`.
+/// Callers serialize access to the cursor.
+#[cfg(any(test, not(any(unix, windows))))]
+fn seek_read(mut file: &std::fs::File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
+}
+
+impl File for LocalFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.read_at(self.pos, buf)?;
+        self.pos += n as u64;
+        if n < buf.len() {
+            self.eof = true;
+        }
+        Ok(n)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        if self.closed {
+            return Err(FsError::FileClosed);
+        }
+        let mut total = 0;
+        while total < buf.len() {
+            match self.pread(&mut buf[total..], offset + total as u64) {
+                Ok(0) => break,
+                Ok(n) => total += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(total)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<()> {
+        if self.closed {
+            return Err(FsError::FileClosed);
+        }
+        self.pos = pos;
+        self.eof = false;
+        Ok(())
+    }
+
+    fn tell(&self) -> u64 {
+        self.pos
+    }
+
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.size)
+    }
+
+    fn url(&self) -> Option<&str> {
+        Some(&self.url)
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Mock, pattern};
+
+    #[test]
+    fn mirrored_files_are_read_from_disk_and_others_over_http() {
+        let root = std::env::temp_dir().join(format!("pravaha-layered-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("data dir")).unwrap();
+        let local: Vec<u8> = (0..5000u32).map(|i| (i % 7) as u8).collect();
+        std::fs::write(root.join("data dir/a.bin"), &local).unwrap();
+        let mock = Mock::new(pattern(4096));
+        let fs = LayeredFileSystem::new(root.to_str().unwrap(), mock.fs().build());
+
+        let mut file = fs
+            .open(
+                "https://cdn.example.com/data%20dir/a.bin?sig=1",
+                OpenMode::Read,
+            )
+            .unwrap();
+        assert_eq!(file.size(), Some(5000));
+        let mut all = Vec::new();
+        let mut buf = [0u8; 777];
+        while let n @ 1.. = file.read(&mut buf).unwrap() {
+            all.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(all, local);
+        assert!(file.eof());
+        assert_eq!(file.read_at(4995, &mut buf).unwrap(), 5);
+        assert_eq!(mock.gets(), 0);
+
+        let mut file = fs
+            .open(
+                "https://cdn.example.com/data%20dir/missing.bin",
+                OpenMode::Read,
+            )
+            .unwrap();
+        assert_eq!(file.read(&mut buf[..100]).unwrap(), 100);
+        assert_eq!(&buf[..100], &pattern(100)[..]);
+        assert_eq!(mock.gets(), 1);
+        assert!(fs.local_path("https://h/../etc/passwd").is_none());
+
+        // The fallback for targets without positional reads agrees.
+        let disk = std::fs::File::open(root.join("data dir/a.bin")).unwrap();
+        assert_eq!(seek_read(&disk, &mut buf[..10], 4995).unwrap(), 5);
+        assert_eq!(&buf[..5], &local[4995..]);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
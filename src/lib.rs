@@ -90,7 +90,8 @@
 //! |  |  * Ready    -> LRU hit, wrap in ready future |   |
 //! |  +---------------------------------------------+   |
 //! |                                                     |
-//! |  Semaphore: caps concurrent HTTP requests           |
+//! |  FetchSlots: caps concurrent HTTP requests, by      |
+//! |    priority (blocked reads > hints > read-ahead)    |
 //! |  PrefetchPlanner: fires read_ahead_chunks futures   |
 //! +--------------------+--------------------------------+
 //!                      |
@@ -121,21 +122,38 @@
 //! - `curl` (default): use libcurl via `spawn_blocking`
 //! - `reqwest`: use async reqwest (don't enable both)
 //! - `capi`: build the C API
+//! - `serde`: `Serialize`/`Deserialize` for `HttpConfig` (durations in ms)
+//! - `tracing`: emit `tracing` debug events for cache evictions
+//! - `gzip`: decode gzip streams with `auto_decompress_sequential`
+//! - `digest`: SHA-2 digests of ranges with `HttpFile::digest_range`
 
 pub mod core;
+#[cfg(feature = "gzip")]
+mod gzip;
 pub mod http;
+pub mod layered;
 pub mod plug;
+pub mod pool;
+mod slots;
+pub mod text;
+mod url;
 
 pub use core::*;
 pub use http::*;
+pub use layered::LayeredFileSystem;
 pub use plug::AsyncHttp;
+pub use pool::{CachedReader, CachedReaderFactory, HttpFilePool, PooledFile};
+pub use text::{DecodedLines, Encoding};
 
 #[cfg(feature = "capi")]
 pub mod ffi;
 
-use std::io::{self, Read, Seek, SeekFrom};
+#[cfg(test)]
+mod mock;
 
-/// Adapts `Box<dyn File>` into `std::io::Read + Seek` for use with
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Adapts `Box<dyn File>` into `std::io::Read + Write + Seek` for use with
 /// third-party libraries (zip, image decoders, etc.).
 pub struct FileAdapter {
     inner: Box<dyn File>,
@@ -162,7 +180,23 @@ impl Read for FileAdapter {
     }
 }
 
-pub(crate) fn resolve_seek(pos: SeekFrom, current: u64, size: Option<u64>) -> io::Result<u64> {
+impl Write for FileAdapter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).map_err(io::Error::other)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().map_err(io::Error::other)
+    }
+}
+
+/// Resolve `pos` to an absolute offset.  `size` is only called for
+/// `SeekFrom::End`, so other variants never trigger a size probe.
+pub(crate) fn resolve_seek(
+    pos: SeekFrom,
+    current: u64,
+    size: impl FnOnce() -> Option<u64>,
+) -> io::Result<u64> {
     match pos {
         SeekFrom::Start(o) => Ok(o),
         SeekFrom::Current(o) => {
@@ -173,7 +207,7 @@ pub(crate) fn resolve_seek(pos: SeekFrom, current: u64, size: Option<u64>) -> io
             }
         }
         SeekFrom::End(o) => {
-            let size = size.ok_or_else(|| {
+            let size = size().ok_or_else(|| {
                 io::Error::new(
                     io::ErrorKind::Unsupported,
                     "Cannot seek from end without known file size",
@@ -190,8 +224,6 @@ pub(crate) fn resolve_seek(pos: SeekFrom, current: u64, size: Option<u64>) -> io
 
 impl Seek for FileAdapter {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        let new_pos = resolve_seek(pos, self.inner.tell(), self.inner.size())?;
-        self.inner.seek(new_pos).map_err(io::Error::other)?;
-        Ok(new_pos)
+        self.inner.seek_from(pos).map_err(io::Error::other)
     }
 }
@@ -0,0 +1,275 @@
+//! An in-memory [`AsyncHttp`] server for the unit tests.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+
+use crate::core::{FsError, Result};
+use crate::http::{HttpFileSystem, HttpFileSystemBuilder};
+use crate::plug::{AsyncHttp, BodySink, HttpResponse, RequestOptions};
+
+/// Serves `data` at every URL not uploaded to: HEADs report its size and
+/// range GETs return the requested bytes with the total size, or a 416 past
+/// the end with just the total.  Plain GETs stream the body in 700 byte
+/// pieces with no length.  Every range GET is recorded, and HEADs counted.
+pub(crate) struct Mock {
+    data: Vec<u8>,
+    ranges: Mutex<Vec<(u64, u64)>>,
+    /// The options each range GET was sent with, in order.
+    options: Mutex<Vec<RequestOptions>>,
+    heads: AtomicUsize,
+    /// Bodies PUT so far, by URL.
+    uploads: Mutex<HashMap<String, Vec<u8>>>,
+    /// URLs answered with a 404.
+    missing: Mutex<HashSet<String>>,
+    /// URLs whose HEADs are answered with a 405.
+    headless: Mutex<HashSet<String>>,
+    /// Returned by the next GETs, one each, in order.
+    errors: Mutex<VecDeque<FsError>>,
+    /// If set, each GET waits here for a permit after it is recorded.
+    gate: Option<tokio::sync::Semaphore>,
+    /// If set, HEADs leave out the size.
+    head_without_length: AtomicBool,
+    /// If set, HEADs never answer.
+    stalled_heads: AtomicBool,
+    /// Sent as the `Content-Type` of range responses, if set.
+    content_type: Mutex<Option<String>>,
+    /// Most bytes sent for one range, if set.
+    max_range: Mutex<Option<u64>>,
+}
+
+impl Mock {
+    pub(crate) fn new(data: Vec<u8>) -> Arc<Self> {
+        Arc::new(Self {
+            data,
+            ranges: Mutex::new(Vec::new()),
+            options: Mutex::new(Vec::new()),
+            heads: AtomicUsize::new(0),
+            uploads: Mutex::new(HashMap::new()),
+            missing: Mutex::new(HashSet::new()),
+            headless: Mutex::new(HashSet::new()),
+            errors: Mutex::new(VecDeque::new()),
+            gate: None,
+            head_without_length: AtomicBool::new(false),
+            stalled_heads: AtomicBool::new(false),
+            content_type: Mutex::new(None),
+            max_range: Mutex::new(None),
+        })
+    }
+
+    /// Like [`Mock::new`], but GETs hang until [`Mock::release`]d.
+    pub(crate) fn gated(data: Vec<u8>) -> Arc<Self> {
+        let mut mock = Arc::into_inner(Self::new(data)).unwrap();
+        mock.gate = Some(tokio::sync::Semaphore::new(0));
+        Arc::new(mock)
+    }
+
+    /// Let `n` more GETs through the gate.
+    pub(crate) fn release(&self, n: usize) {
+        if let Some(gate) = &self.gate {
+            gate.add_permits(n);
+        }
+    }
+
+    /// Fail a coming GET with `e`, after any failures queued before it.
+    pub(crate) fn fail_next(&self, e: FsError) {
+        self.errors.lock().unwrap().push_back(e);
+    }
+
+    /// Leave the size out of every later HEAD, as some servers do.
+    pub(crate) fn hide_head_length(&self) {
+        self.head_without_length.store(true, Ordering::SeqCst);
+    }
+
+    /// Leave every later HEAD unanswered, as a hung server would.
+    pub(crate) fn stall_heads(&self) {
+        self.stalled_heads.store(true, Ordering::SeqCst);
+    }
+
+    /// Label every later range response as `content_type`.
+    pub(crate) fn serve_as(&self, content_type: &str) {
+        *self.content_type.lock().unwrap() = Some(content_type.to_string());
+    }
+
+    /// Send at most `len` bytes for every later range, with a
+    /// `Content-Range` that says so, as servers with a response size limit
+    /// do.
+    pub(crate) fn cap_ranges(&self, len: u64) {
+        *self.max_range.lock().unwrap() = Some(len);
+    }
+
+    /// Answer every later request for `url` with a 404.
+    pub(crate) fn remove(&self, url: &str) {
+        self.missing.lock().unwrap().insert(url.to_string());
+    }
+
+    /// Answer every later HEAD of `url` with a 405, as servers that only
+    /// serve GETs do.
+    pub(crate) fn refuse_heads(&self, url: &str) {
+        self.headless.lock().unwrap().insert(url.to_string());
+    }
+
+    fn is_missing(&self, url: &str) -> bool {
+        self.missing.lock().unwrap().contains(url)
+    }
+
+    /// The object the last PUT to `url` left behind.
+    pub(crate) fn uploaded(&self, url: &str) -> Option<Vec<u8>> {
+        self.uploads.lock().unwrap().get(url).cloned()
+    }
+
+    fn body(&self, url: &str) -> Vec<u8> {
+        self.uploaded(url).unwrap_or_else(|| self.data.clone())
+    }
+
+    pub(crate) fn gets(&self) -> usize {
+        self.ranges.lock().unwrap().len()
+    }
+
+    pub(crate) fn heads(&self) -> usize {
+        self.heads.load(Ordering::SeqCst)
+    }
+
+    /// The ranges requested so far, in order.
+    pub(crate) fn ranges(&self) -> Vec<(u64, u64)> {
+        self.ranges.lock().unwrap().clone()
+    }
+
+    /// Header `name` as sent with each range GET so far, in order.
+    pub(crate) fn sent_header(&self, name: &str) -> Vec<Option<String>> {
+        let options = self.options.lock().unwrap();
+        let find = |opts: &RequestOptions| {
+            let header = opts
+                .headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name));
+            header.map(|(_, v)| v.clone())
+        };
+        options.iter().map(find).collect()
+    }
+
+    /// The body each range GET carried so far, in order.  A transport sends
+    /// a request with a body as a POST.
+    pub(crate) fn bodies(&self) -> Vec<Option<Vec<u8>>> {
+        let options = self.options.lock().unwrap();
+        options
+            .iter()
+            .map(|o| o.body.as_deref().map(<[u8]>::to_vec))
+            .collect()
+    }
+
+    /// A filesystem on this server with 1 KiB chunks and no read-ahead, so
+    /// each test opts into just the requests it is about.
+    pub(crate) fn fs(self: &Arc<Self>) -> HttpFileSystemBuilder {
+        HttpFileSystem::builder()
+            .chunk_size(1024)
+            .min_chunk_size(0)
+            .read_ahead_chunks(0)
+            .transport(Arc::clone(self) as Arc<dyn AsyncHttp>)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncHttp for Mock {
+    async fn get_content_length(&self, url: &str) -> Result<Option<u64>> {
+        self.heads.fetch_add(1, Ordering::SeqCst);
+        if self.is_missing(url) || self.head_without_length.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        Ok(Some(self.body(url).len() as u64))
+    }
+
+    async fn head_with(&self, url: &str, _opts: &RequestOptions) -> Result<HttpResponse> {
+        self.heads.fetch_add(1, Ordering::SeqCst);
+        if self.stalled_heads.load(Ordering::SeqCst) {
+            std::future::pending::<()>().await;
+        }
+        if self.is_missing(url) {
+            return Ok(HttpResponse::new(Vec::new(), 404, None, None, None));
+        }
+        if self.headless.lock().unwrap().contains(url) {
+            return Ok(HttpResponse::new(Vec::new(), 405, None, None, None));
+        }
+        let size = Some(self.body(url).len() as u64)
+            .filter(|_| !self.head_without_length.load(Ordering::SeqCst));
+        Ok(HttpResponse::new(Vec::new(), 200, size, None, None))
+    }
+
+    async fn get_range(&self, url: &str, start: u64, end: u64) -> Result<HttpResponse> {
+        self.ranges.lock().unwrap().push((start, end));
+        if let Some(gate) = &self.gate {
+            gate.acquire().await.unwrap().forget();
+        }
+        if let Some(e) = self.errors.lock().unwrap().pop_front() {
+            return Err(e);
+        }
+        if self.is_missing(url) {
+            return Err(FsError::Http { status: 404 });
+        }
+        let data = self.body(url);
+        let len = data.len() as u64;
+        if start >= len {
+            return Ok(HttpResponse::new(Vec::new(), 416, None, None, None)
+                .with_headers(vec![("Content-Range".into(), format!("bytes */{len}"))]));
+        }
+        let mut end = end.min(len - 1);
+        if let Some(max) = *self.max_range.lock().unwrap() {
+            end = end.min(start + max - 1);
+        }
+        let body = data[start as usize..=end as usize].to_vec();
+        let mut headers = vec![(
+            "Content-Range".to_string(),
+            format!("bytes {start}-{end}/{len}"),
+        )];
+        if let Some(content_type) = self.content_type.lock().unwrap().clone() {
+            headers.push(("Content-Type".into(), content_type));
+        }
+        Ok(
+            HttpResponse::new(body, 206, Some(end - start + 1), Some((start, end)), None)
+                .with_headers(headers),
+        )
+    }
+
+    async fn get_range_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        opts: &RequestOptions,
+    ) -> Result<HttpResponse> {
+        self.options.lock().unwrap().push(opts.clone());
+        self.get_range(url, start, end).await
+    }
+
+    async fn get_stream_with(
+        &self,
+        url: &str,
+        _opts: &RequestOptions,
+        sink: BodySink,
+    ) -> Result<()> {
+        if self.is_missing(url) {
+            return Err(FsError::Http { status: 404 });
+        }
+        for piece in self.body(url).chunks(700) {
+            if sink.send(piece.to_vec()).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn put_with(&self, url: &str, body: Bytes, _opts: &RequestOptions) -> Result<()> {
+        self.uploads
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), body.to_vec());
+        Ok(())
+    }
+}
+
+/// `len` bytes that differ from their neighbours, so misplaced data shows.
+pub(crate) fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
@@ -1,10 +1,17 @@
 #![allow(dead_code)]
 
 use crate::core::{FsError, Result};
-use crate::http::HttpConfig;
+use crate::http::{HttpConfig, IpFamily};
+use bytes::Bytes;
 use std::sync::Arc;
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+use std::sync::atomic::{AtomicBool, Ordering};
 
+/// A response as reported by a transport.  Transports outside this crate
+/// build one with [`HttpResponse::new`] and the `with_*` setters, so fields
+/// can be added without breaking them.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct HttpResponse {
     pub data: Vec<u8>,
     pub status: u16,
@@ -12,6 +19,15 @@ pub struct HttpResponse {
     pub content_range: Option<(u64, u64)>,
     /// Parsed Retry-After value in seconds, if server sent one.
     pub retry_after_secs: Option<u64>,
+    /// All response headers, names lowercased.
+    pub headers: Vec<(String, String)>,
+    /// Protocol version of the response, e.g. `"HTTP/1.1"` or `"HTTP/2"`.
+    pub http_version: Option<String>,
+    /// When the first body byte arrived, if the transport tracks it.
+    pub first_byte_at: Option<std::time::Instant>,
+    /// URL the response finally came from after following redirects, if
+    /// the transport reports it.
+    pub effective_url: Option<String>,
 }
 
 impl HttpResponse {
@@ -28,15 +44,351 @@ impl HttpResponse {
             content_length,
             content_range,
             retry_after_secs,
+            headers: Vec::new(),
+            http_version: None,
+            first_byte_at: None,
+            effective_url: None,
         }
     }
+
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_http_version(mut self, version: impl Into<String>) -> Self {
+        self.http_version = Some(version.into());
+        self
+    }
+
+    pub fn with_first_byte_at(mut self, at: std::time::Instant) -> Self {
+        self.first_byte_at = Some(at);
+        self
+    }
+
+    pub fn with_effective_url(mut self, url: impl Into<String>) -> Self {
+        self.effective_url = Some(url.into());
+        self
+    }
+
+    /// The `Content-Encoding` applied to the body, unless it is `identity`.
+    /// When set, `content_length` and any byte ranges refer to the encoded
+    /// bytes, not the resource itself.
+    pub fn content_encoding(&self) -> Option<&str> {
+        self.header("content-encoding")
+            .map(str::trim)
+            .filter(|e| !e.is_empty() && !e.eq_ignore_ascii_case("identity"))
+    }
+
+    /// The boundary of a `multipart/byteranges` body, which some proxies
+    /// send even for a single-range request.
+    pub fn multipart_boundary(&self) -> Option<&str> {
+        multipart_boundary(self.header("content-type")?)
+    }
+
+    /// The full size of the resource from the `/total` part of a
+    /// `Content-Range` header, unless the server sent `*`.
+    pub fn complete_length(&self) -> Option<u64> {
+        let (_, total) = self.header("content-range")?.trim().rsplit_once('/')?;
+        total.trim().parse().ok()
+    }
+
+    /// The value of header `name` (case-insensitive), if present.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Per-request options handed to the transport.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RequestOptions {
+    /// Extra request headers, already merged from the filesystem defaults
+    /// and any per-file overrides.
+    pub headers: Vec<(String, String)>,
+    /// Body of a resource only served to POST requests, see
+    /// [`HttpFileSystem::open_post`].  When set, range and streaming GETs
+    /// are sent as POSTs carrying this body; HEAD and PUT ignore it.
+    /// Transports that only implement the plain methods never send it.
+    ///
+    /// [`HttpFileSystem::open_post`]: crate::HttpFileSystem::open_post
+    pub body: Option<Arc<[u8]>>,
+    /// Replaces the transport's `read_timeout` for this file, see
+    /// [`FileOverrides`](crate::FileOverrides).
+    pub read_timeout: Option<std::time::Duration>,
+    /// Replaces the transport's `metadata_timeout` for this file.
+    pub metadata_timeout: Option<std::time::Duration>,
+}
+
+impl RequestOptions {
+    pub fn with_body(mut self, body: impl Into<Arc<[u8]>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    pub fn with_read_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_metadata_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.metadata_timeout = Some(timeout);
+        self
+    }
+}
+
+/// Merge `overrides` over `base`; header names compare case-insensitively.
+pub(crate) fn merge_headers(
+    base: &[(String, String)],
+    overrides: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = base
+        .iter()
+        .filter(|(name, _)| !overrides.iter().any(|(o, _)| o.eq_ignore_ascii_case(name)))
+        .cloned()
+        .collect();
+    out.extend(overrides.iter().cloned());
+    out
 }
 
+/// Receives the body of a streamed GET, see [`AsyncHttp::get_stream_with`].
+pub type BodySink = tokio::sync::mpsc::Sender<Vec<u8>>;
+
 /// Async transport trait — internal only.
+///
+/// The `*_with` variants receive per-request options; their default
+/// implementations ignore the options and call the plain methods, so custom
+/// transports only need to implement the two required methods.
+///
+/// Retries are decided by the error variant a method returns, never by its
+/// message: a failure to reach the server or to receive the whole response
+/// should be [`FsError::Network`], which is retried; a response with an
+/// error status should be returned as-is (or as [`FsError::Http`]), so that
+/// only 5xx and 408 are retried.  Other variants are final.
 #[async_trait::async_trait]
 pub trait AsyncHttp: Send + Sync {
     async fn get_content_length(&self, url: &str) -> Result<Option<u64>>;
     async fn get_range(&self, url: &str, start: u64, end: u64) -> Result<HttpResponse>;
+
+    async fn get_content_length_with(
+        &self,
+        url: &str,
+        opts: &RequestOptions,
+    ) -> Result<Option<u64>> {
+        let _ = opts;
+        self.get_content_length(url).await
+    }
+
+    /// Issue a HEAD request and return the response, whatever its status.
+    /// The default implementation is built on `get_content_length_with` and
+    /// reports a 200 with no headers.
+    async fn head_with(&self, url: &str, opts: &RequestOptions) -> Result<HttpResponse> {
+        let content_length = self.get_content_length_with(url, opts).await?;
+        Ok(HttpResponse::new(
+            Vec::new(),
+            200,
+            content_length,
+            None,
+            None,
+        ))
+    }
+
+    async fn get_range_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        opts: &RequestOptions,
+    ) -> Result<HttpResponse> {
+        let _ = opts;
+        self.get_range(url, start, end).await
+    }
+
+    /// Like `get_range_with`, but writes the body into `buf` and returns the
+    /// number of bytes written (0 for a 416).  A body longer than `buf` is a
+    /// protocol error.  On error the contents of `buf` are unspecified.  The
+    /// default implementation copies out of `get_range_with`'s response.
+    async fn get_range_into_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        buf: &mut [u8],
+        opts: &RequestOptions,
+    ) -> Result<usize> {
+        let resp = self.get_range_with(url, start, end, opts).await?;
+        check_body_complete(&resp, resp.data.len(), start, end)?;
+        let dst = buf.get_mut(..resp.data.len()).ok_or_else(body_overflow)?;
+        dst.copy_from_slice(&resp.data);
+        Ok(resp.data.len())
+    }
+
+    /// Plain GET of the whole of `url`, for bodies of unknown length or
+    /// from servers without range support.  Each piece of the body is sent
+    /// to `sink` as it arrives; once sending fails the reader is gone and
+    /// the transfer should stop.  A response that is not a 2xx is an error
+    /// and none of its body is sent.  The default implementation reports
+    /// streaming as unsupported.
+    async fn get_stream_with(
+        &self,
+        url: &str,
+        opts: &RequestOptions,
+        sink: BodySink,
+    ) -> Result<()> {
+        let _ = (url, opts, sink);
+        Err(FsError::Protocol(
+            "This transport does not support streaming".into(),
+        ))
+    }
+
+    /// Upload `body` as the complete contents of `url` (HTTP PUT).  `body`
+    /// is shared with the file that wrote it, so keeping it costs no copy.
+    /// The default implementation reports uploads as unsupported.
+    async fn put_with(&self, url: &str, body: Bytes, opts: &RequestOptions) -> Result<()> {
+        let _ = (url, body, opts);
+        Err(FsError::Protocol(
+            "This transport does not support uploads".into(),
+        ))
+    }
+
+    /// Stop and join any threads of the transport's own, once
+    /// [`shutdown`](crate::shutdown) has cancelled the work using them.
+    /// Requests made afterwards may fail.  Does nothing by default.
+    fn shutdown(&self) {}
+}
+
+/// Message of the error for a range request answered with the whole body.
+pub(crate) const RANGES_NOT_SUPPORTED: &str = "Server does not support Range requests (returned 200 instead of 206). \
+     This library requires strict Range semantics.";
+
+pub(crate) fn body_overflow() -> FsError {
+    FsError::Protocol("Server sent more data than the requested range".into())
+}
+
+/// Fail a response to a PUT or a plain (non-range) GET that did not succeed.
+pub(crate) fn validate_status(status: u16, retry_after_secs: Option<u64>) -> Result<()> {
+    if status == 429 || status == 503 {
+        return Err(FsError::RateLimited { retry_after_secs });
+    }
+    if !(200..300).contains(&status) {
+        return Err(FsError::Http { status });
+    }
+    Ok(())
+}
+
+/// Map a curl failure by its code.  A URL curl cannot parse, a scheme it
+/// was built without and an option it rejects mean the request itself is
+/// wrong, and become `InvalidUrl`, `UnsupportedProtocol` and `Io`, which are
+/// not retried.  Everything else went wrong on the way to or from the server
+/// (resolving, connecting, TLS, sending, receiving, timing out) and is a
+/// retried `Network` error.  Statuses never come through here: responses
+/// are always read, and their status checked on its own.
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+pub(crate) fn curl_error(e: curl::Error) -> FsError {
+    if e.is_url_malformed() {
+        FsError::InvalidUrl(e.to_string())
+    } else if e.is_unsupported_protocol() {
+        FsError::UnsupportedProtocol(e.to_string())
+    } else if e.is_unknown_option() || e.is_bad_function_argument() || e.is_failed_init() {
+        FsError::Io(e.to_string())
+    } else {
+        FsError::Network(e.to_string())
+    }
+}
+
+/// Map a reqwest failure like [`curl_error`]: a request that could not be
+/// built (bad URL or header) is `Io`, an error status is `Http`, and any
+/// other failure is a retried `Network` error.
+#[cfg(feature = "reqwest")]
+pub(crate) fn reqwest_error(e: reqwest::Error) -> FsError {
+    if let Some(status) = e.status() {
+        FsError::Http {
+            status: status.as_u16(),
+        }
+    } else if e.is_builder() {
+        FsError::Io(e.to_string())
+    } else {
+        FsError::Network(e.to_string())
+    }
+}
+
+/// Fail a 3xx response, which is only seen when redirects are not followed.
+pub(crate) fn reject_redirect(status: u16, location: Option<&str>) -> Result<()> {
+    if (300..400).contains(&status) && status != 304 {
+        return Err(FsError::Protocol(format!(
+            "Unexpected redirect to {}",
+            location.unwrap_or("<no Location header>")
+        )));
+    }
+    Ok(())
+}
+
+/// A proxy from [`HttpConfig::proxy`] in the form the transports take.
+/// Not `Debug`, since the URL may carry the proxy's password.
+#[derive(Clone)]
+pub(crate) struct ProxySpec {
+    /// `http://`, `https://` or `socks5h://`, followed by any credentials
+    /// and the host.
+    pub(crate) url: String,
+    pub(crate) socks: bool,
+}
+
+impl ProxySpec {
+    /// Check `url` and map it for the transports.  A `socks5://` proxy is
+    /// asked to resolve host names itself, as the other end of a tunnel can
+    /// often reach hosts this machine cannot resolve.  Errors never echo the
+    /// URL, which may contain a password.
+    pub(crate) fn parse(url: &str) -> Result<Self> {
+        let invalid = || {
+            FsError::InvalidUrl(
+                "Proxy URL must have the form scheme://[user:password@]host[:port]".into(),
+            )
+        };
+        let scheme = crate::url::scheme(url).ok_or_else(invalid)?;
+        let (mapped, socks) = match scheme.to_ascii_lowercase().as_str() {
+            "http" => ("http", false),
+            "https" => ("https", false),
+            "socks5" | "socks5h" => ("socks5h", true),
+            _ => {
+                return Err(FsError::UnsupportedProtocol(format!(
+                    "{scheme} proxies (use http, https or socks5)"
+                )));
+            }
+        };
+        let rest = url[scheme.len() + 3..].trim_end_matches('/');
+        let host = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+        let (name, port) = match host.strip_prefix('[') {
+            Some(v6) => {
+                let (addr, port) = v6.split_once(']').ok_or_else(invalid)?;
+                let addr_ok = !addr.is_empty()
+                    && addr
+                        .chars()
+                        .all(|c| c.is_ascii_hexdigit() || matches!(c, ':' | '.'));
+                (addr_ok.then_some(addr), port.strip_prefix(':'))
+            }
+            None => match host.rsplit_once(':') {
+                Some((name, port)) => (Some(name), Some(port)),
+                None => (Some(host), None),
+            },
+        };
+        let name_ok = name.is_some_and(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | ':'))
+        });
+        let port_ok = port.is_none_or(|port| port.parse::<u16>().is_ok());
+        if !(name_ok && port_ok) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            url: format!("{mapped}://{rest}"),
+            socks,
+        })
+    }
 }
 
 pub(crate) fn build_default_transport(config: &HttpConfig) -> Arc<dyn AsyncHttp> {
@@ -51,9 +403,15 @@ pub(crate) fn build_default_transport(config: &HttpConfig) -> Arc<dyn AsyncHttp>
     }
 }
 
-pub(crate) fn parse_content_range(header: &str) -> Option<(u64, u64)> {
+/// A `Range` header value asking for `start..=end` in range unit `unit`.
+pub(crate) fn range_header(unit: &str, start: u64, end: u64) -> String {
+    format!("{unit}={start}-{end}")
+}
+
+/// The `start-end` span of a `Content-Range` header in range unit `unit`.
+pub(crate) fn parse_content_range(header: &str, unit: &str) -> Option<(u64, u64)> {
     let parts: Vec<&str> = header.split_whitespace().collect();
-    if parts.len() < 2 || parts[0] != "bytes" {
+    if parts.len() < 2 || !parts[0].eq_ignore_ascii_case(unit) {
         return None;
     }
     let range_part = parts[1].split('/').next()?;
@@ -63,6 +421,113 @@ pub(crate) fn parse_content_range(header: &str) -> Option<(u64, u64)> {
     Some((start, end))
 }
 
+fn multipart_boundary(content_type: &str) -> Option<&str> {
+    let mut params = content_type.split(';');
+    let mime = params.next()?.trim();
+    if !mime.eq_ignore_ascii_case("multipart/byteranges") {
+        return None;
+    }
+    params.find_map(|p| {
+        let (name, value) = p.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+fn malformed_multipart(what: &str) -> FsError {
+    FsError::Protocol(format!("Malformed multipart/byteranges response: {what}"))
+}
+
+/// Extract the file bytes from a `multipart/byteranges` body sent for a
+/// single range starting at `start`.  The parts must cover one contiguous
+/// span from `start`; the MIME framing is discarded.  Returns the payload
+/// and the span it covers.  Part ranges must be in range unit `unit`.
+pub(crate) fn parse_byteranges(
+    body: &[u8],
+    boundary: &str,
+    start: u64,
+    unit: &str,
+) -> Result<(Vec<u8>, (u64, u64))> {
+    let delimiter = format!("--{boundary}");
+    let delimiter = delimiter.as_bytes();
+    // The first delimiter is on a line of its own, after any preamble.
+    let first = (0..body.len())
+        .find(|&i| (i == 0 || body[i - 1] == b'\n') && body[i..].starts_with(delimiter))
+        .ok_or_else(|| malformed_multipart("no boundary"))?;
+
+    let mut rest = &body[first..];
+    let mut payload = Vec::new();
+    let mut next = start;
+    loop {
+        // `rest` starts at a delimiter line: the closing one, or one that
+        // opens a part, whose headers run up to the first empty line.
+        rest = &rest[delimiter.len()..];
+        if rest.starts_with(b"--") {
+            break;
+        }
+        let unterminated = || malformed_multipart("unterminated part headers");
+        (_, rest) = split_line(rest).ok_or_else(unterminated)?;
+        let mut range = None;
+        loop {
+            let (line, after) = split_line(rest).ok_or_else(unterminated)?;
+            rest = after;
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with(delimiter) {
+                return Err(malformed_multipart("part without a body"));
+            }
+            if let Some((name, value)) = std::str::from_utf8(line)
+                .ok()
+                .and_then(|line| line.split_once(':'))
+                && name.trim().eq_ignore_ascii_case("content-range")
+            {
+                range = parse_content_range(value.trim(), unit);
+            }
+        }
+        let (first, last) =
+            range.ok_or_else(|| malformed_multipart("part without Content-Range"))?;
+        if first != next {
+            return Err(malformed_multipart(&format!(
+                "part {first}-{last} does not continue at offset {next}"
+            )));
+        }
+        let bad_range = || malformed_multipart(&format!("invalid part range {first}-{last}"));
+        let len = last
+            .checked_sub(first)
+            .and_then(|n| n.checked_add(1))
+            .ok_or_else(bad_range)?;
+        next = last.checked_add(1).ok_or_else(bad_range)?;
+        let len = usize::try_from(len).map_err(|_| malformed_multipart("part too large"))?;
+        let data = rest
+            .get(..len)
+            .ok_or_else(|| malformed_multipart("truncated part"))?;
+        payload.extend_from_slice(data);
+        rest = &rest[len..];
+        let skip = rest
+            .iter()
+            .take_while(|b| matches!(b, b'\r' | b'\n'))
+            .count();
+        rest = &rest[skip..];
+        if !rest.starts_with(delimiter) {
+            return Err(malformed_multipart("part not followed by a boundary"));
+        }
+    }
+    if payload.is_empty() {
+        return Err(malformed_multipart("no parts"));
+    }
+    Ok((payload, (start, next - 1)))
+}
+
+/// The line at the start of `s`, without its `\r\n` or `\n`, and what
+/// follows it.  `None` if the line is not terminated.
+fn split_line(s: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = s.iter().position(|&b| b == b'\n')?;
+    let line = &s[..end];
+    Some((line.strip_suffix(b"\r").unwrap_or(line), &s[end + 1..]))
+}
+
 /// Parse Retry-After either an integer seconds value or an HTTP-date.
 pub(crate) fn parse_retry_after(header: &str) -> Option<u64> {
     // Try plain integer first.
@@ -73,6 +538,87 @@ pub(crate) fn parse_retry_after(header: &str) -> Option<u64> {
     Some(5)
 }
 
+/// Body length a 206 response announced in its `Content-Range`, or failing
+/// that its `Content-Length`.
+fn declared_len(resp: &HttpResponse) -> Option<u64> {
+    resp.content_range
+        .map(|(s, e)| e.saturating_sub(s).saturating_add(1))
+        .or(resp.content_length)
+}
+
+/// Fail a 206 body of `len` bytes for `start..=end` that is shorter than its
+/// headers announced.  It was cut off in transit, e.g. by a connection
+/// closed early, so this is a network error and the request is retried
+/// rather than the short body used as if complete.
+pub(crate) fn check_body_complete(
+    resp: &HttpResponse,
+    len: usize,
+    start: u64,
+    end: u64,
+) -> Result<()> {
+    if resp.status != 206 {
+        return Ok(());
+    }
+    let len = len as u64;
+    if let Some(declared) = declared_len(resp)
+        && len < declared
+    {
+        return Err(FsError::Network(format!(
+            "Body for range {start}-{end} ended after {len} of {declared} bytes"
+        )));
+    }
+    Ok(())
+}
+
+/// Check a 206 body for `start..=end` against its headers.  A body shorter
+/// than its `Content-Range`/`Content-Length` fails as in
+/// [`check_body_complete`].  A body longer than those or than the requested
+/// range is truncated (or, if `strict`, rejected as a protocol error).
+pub(crate) fn check_range_body(
+    resp: &mut HttpResponse,
+    start: u64,
+    end: u64,
+    strict: bool,
+) -> Result<()> {
+    if resp.status != 206 {
+        return Ok(());
+    }
+    check_body_complete(resp, resp.data.len(), start, end)?;
+    let len = resp.data.len() as u64;
+    let declared = declared_len(resp);
+    let requested = end.saturating_sub(start).saturating_add(1);
+    let limit = declared.map_or(requested, |d| d.min(requested));
+    if len > limit {
+        if strict {
+            return Err(FsError::Protocol(format!(
+                "Server sent {len} bytes for range {start}-{end}, expected at most {limit}"
+            )));
+        }
+        #[cfg(debug_assertions)]
+        eprintln!(
+            "[pravaha] server sent {len} bytes for range {start}-{end}, truncating to {limit}"
+        );
+        resp.data.truncate(limit as usize);
+    }
+    Ok(())
+}
+
+/// With `reject`, fail a 2xx response labelled `text/html`: for a file
+/// read that is a directory listing or an error page, not file data.
+pub(crate) fn check_not_html(reject: bool, status: u16, content_type: Option<&str>) -> Result<()> {
+    let html = content_type.is_some_and(|ct| {
+        ct.split(';')
+            .next()
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/html"))
+    });
+    if reject && html && (200..300).contains(&status) {
+        return Err(FsError::Protocol(
+            "expected binary content, got text/html".into(),
+        ));
+    }
+    Ok(())
+}
+
 pub(crate) fn validate_range_response(
     status: u16,
     content_range: Option<(u64, u64)>,
@@ -87,14 +633,10 @@ pub(crate) fn validate_range_response(
         return Ok(());
     }
     if status == 200 {
-        return Err(FsError::Protocol(
-            "Server does not support Range requests (returned 200 instead of 206). \
-             This library requires strict Range semantics."
-                .into(),
-        ));
+        return Err(FsError::Protocol(RANGES_NOT_SUPPORTED.into()));
     }
     if status != 206 {
-        return Err(FsError::Network(format!("HTTP error: {status}")));
+        return Err(FsError::Http { status });
     }
     if let Some((resp_start, _)) = content_range
         && resp_start != requested_start
@@ -109,47 +651,113 @@ pub(crate) fn validate_range_response(
 #[cfg(feature = "reqwest")]
 pub(crate) struct ReqwestAsyncTransport {
     client: reqwest::Client,
+    read_timeout: std::time::Duration,
+    metadata_timeout: std::time::Duration,
+    upload_timeout: std::time::Duration,
+    follow_redirects: bool,
+    reject_html: bool,
+    range_unit: String,
 }
 
 #[cfg(feature = "reqwest")]
 impl ReqwestAsyncTransport {
     pub fn new(config: &HttpConfig) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(config.read_timeout)
+        // No client-wide total timeout: range GETs use `read_timeout` as an
+        // idle timeout instead (see `read_body`), and other requests set
+        // their own.
+        let mut builder = reqwest::Client::builder()
+            .user_agent(&config.user_agent)
             .connect_timeout(config.connect_timeout)
-            .pool_idle_timeout(config.idle_timeout)
+            .pool_idle_timeout(config.idle_timeout);
+        if !config.follow_redirects {
+            builder = builder.redirect(reqwest::redirect::Policy::none());
+        }
+        // Binding the local socket to an unspecified address of one family
+        // restricts outgoing connections to that family.
+        builder = match config.ip_family {
+            IpFamily::Auto => builder,
+            IpFamily::V4Only => {
+                builder.local_address(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED))
+            }
+            IpFamily::V6Only => {
+                builder.local_address(std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED))
+            }
+        };
+        // An invalid proxy is skipped here; opening any file reports it
+        // instead, so no request goes out without the proxy.
+        if let Some(proxy) = config
+            .proxy
+            .as_deref()
+            .and_then(|p| ProxySpec::parse(p).ok())
+        {
+            let proxy = reqwest::Proxy::all(&proxy.url).expect("ProxySpec URLs are valid");
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
             .build()
             .expect("Failed to build async reqwest client");
-        Self { client }
+        Self {
+            client,
+            read_timeout: config.read_timeout,
+            metadata_timeout: config.metadata_timeout,
+            upload_timeout: config.upload_timeout,
+            follow_redirects: config.follow_redirects,
+            reject_html: config.reject_html,
+            range_unit: config.range_unit.clone(),
+        }
     }
-}
 
-#[cfg(feature = "reqwest")]
-#[async_trait::async_trait]
-impl AsyncHttp for ReqwestAsyncTransport {
-    async fn get_content_length(&self, url: &str) -> Result<Option<u64>> {
-        let resp = self
-            .client
-            .head(url)
-            .send()
-            .await
-            .map_err(|e| FsError::Network(e.to_string()))?;
+    /// A GET of `url` with `opts`' headers, or a POST if `opts` has a body.
+    fn get_or_post(&self, url: &str, opts: &RequestOptions) -> reqwest::RequestBuilder {
+        let mut req = match &opts.body {
+            Some(body) => self
+                .client
+                .post(url)
+                .body(Bytes::from_owner(Arc::clone(body))),
+            None => self.client.get(url),
+        };
+        for (name, value) in &opts.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        req
+    }
 
-        if !resp.status().is_success() {
-            return Ok(None);
+    /// `read_timeout`, unless `opts` overrides it.
+    fn read_timeout(&self, opts: &RequestOptions) -> std::time::Duration {
+        opts.read_timeout.unwrap_or(self.read_timeout)
+    }
+
+    /// Fail a redirect response if redirects are not being followed.
+    fn check_redirect(&self, resp: &reqwest::Response) -> Result<()> {
+        if self.follow_redirects {
+            return Ok(());
         }
-        Ok(resp.content_length())
+        let location = resp.headers().get("location").and_then(|v| v.to_str().ok());
+        reject_redirect(resp.status().as_u16(), location)
     }
+}
 
-    async fn get_range(&self, url: &str, start: u64, end: u64) -> Result<HttpResponse> {
-        let resp = self
-            .client
-            .get(url)
-            .header("Range", format!("bytes={start}-{end}"))
-            .send()
+#[cfg(feature = "reqwest")]
+impl ReqwestAsyncTransport {
+    /// Send a range GET and check the response status and headers.  The
+    /// returned `HttpResponse` has everything but the body.
+    async fn send_range(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        opts: &RequestOptions,
+    ) -> Result<(reqwest::Response, HttpResponse)> {
+        let send = self
+            .get_or_post(url, opts)
+            .header("Range", range_header(&self.range_unit, start, end))
+            .send();
+        let resp = tokio::time::timeout(self.read_timeout(opts), send)
             .await
-            .map_err(|e| FsError::Network(e.to_string()))?;
+            .map_err(|_| FsError::Network("Timed out waiting for response headers".into()))?
+            .map_err(reqwest_error)?;
 
+        self.check_redirect(&resp)?;
         let status = resp.status().as_u16();
         let content_length = resp.content_length();
         let retry_after_secs = resp
@@ -161,40 +769,273 @@ impl AsyncHttp for ReqwestAsyncTransport {
             .headers()
             .get("content-range")
             .and_then(|v| v.to_str().ok())
-            .and_then(parse_content_range);
+            .and_then(|v| parse_content_range(v, &self.range_unit));
+        let content_type = resp
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok());
 
+        check_not_html(self.reject_html, status, content_type)?;
         validate_range_response(status, content_range, start, retry_after_secs)?;
 
-        if status == 416 {
-            return Ok(HttpResponse {
-                data: Vec::new(),
-                status,
-                content_length,
-                content_range,
-                retry_after_secs,
-            });
+        let mut out = HttpResponse::new(
+            Vec::new(),
+            status,
+            content_length,
+            content_range,
+            retry_after_secs,
+        );
+        out.headers = reqwest_headers(&resp);
+        out.http_version = Some(format!("{:?}", resp.version()));
+        out.effective_url = Some(resp.url().to_string());
+        Ok((resp, out))
+    }
+
+    /// Stream the body into `on_chunk`, failing only if no data arrives for
+    /// `timeout`.  A slow but steady transfer can take as long as it needs.
+    async fn read_body(
+        &self,
+        mut resp: reqwest::Response,
+        timeout: std::time::Duration,
+        mut on_chunk: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let mut received = 0;
+        while let Some(chunk) = self.next_chunk(&mut resp, received, timeout).await? {
+            received += chunk.as_ref().len();
+            on_chunk(chunk.as_ref())?;
         }
+        Ok(())
+    }
+
+    /// The next piece of `resp`'s body, or `None` at its end.  Fails if
+    /// nothing arrives for `timeout`; `received` is only reported in that
+    /// error.
+    async fn next_chunk(
+        &self,
+        resp: &mut reqwest::Response,
+        received: usize,
+        timeout: std::time::Duration,
+    ) -> Result<Option<impl AsRef<[u8]>>> {
+        match tokio::time::timeout(timeout, resp.chunk()).await {
+            Ok(chunk) => chunk.map_err(reqwest_error),
+            Err(_) => Err(FsError::Network(format!(
+                "No data received for {timeout:?} after {received} bytes"
+            ))),
+        }
+    }
+
+    /// Like `read_body`, but unwraps a multipart/byteranges body first so
+    /// `on_chunk` only sees file bytes, and updates `out` to match.
+    async fn read_range_body(
+        &self,
+        resp: reqwest::Response,
+        out: &mut HttpResponse,
+        start: u64,
+        timeout: std::time::Duration,
+        mut on_chunk: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let boundary = out.multipart_boundary().map(str::to_owned);
+        let mut first_byte_at = None;
+        let mut framed = Vec::new();
+        self.read_body(resp, timeout, |chunk| {
+            first_byte_at.get_or_insert_with(std::time::Instant::now);
+            if boundary.is_some() {
+                framed.extend_from_slice(chunk);
+                Ok(())
+            } else {
+                on_chunk(chunk)
+            }
+        })
+        .await?;
+        out.first_byte_at = first_byte_at;
+        let Some(boundary) = boundary else {
+            return Ok(());
+        };
+        let (payload, span) = parse_byteranges(&framed, &boundary, start, &self.range_unit)?;
+        out.content_length = Some(payload.len() as u64);
+        out.content_range = Some(span);
+        on_chunk(&payload)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn reqwest_headers(resp: &reqwest::Response) -> Vec<(String, String)> {
+    resp.headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect()
+}
 
-        let data = resp
-            .bytes()
+#[cfg(feature = "reqwest")]
+#[async_trait::async_trait]
+impl AsyncHttp for ReqwestAsyncTransport {
+    async fn get_content_length(&self, url: &str) -> Result<Option<u64>> {
+        self.get_content_length_with(url, &RequestOptions::default())
             .await
-            .map_err(|e| FsError::Network(e.to_string()))?
-            .to_vec();
+    }
 
-        Ok(HttpResponse {
-            data,
-            status,
+    async fn get_range(&self, url: &str, start: u64, end: u64) -> Result<HttpResponse> {
+        self.get_range_with(url, start, end, &RequestOptions::default())
+            .await
+    }
+
+    async fn get_content_length_with(
+        &self,
+        url: &str,
+        opts: &RequestOptions,
+    ) -> Result<Option<u64>> {
+        let resp = self.head_with(url, opts).await?;
+        if !(200..300).contains(&resp.status) {
+            return Ok(None);
+        }
+        Ok(resp.content_length)
+    }
+
+    async fn head_with(&self, url: &str, opts: &RequestOptions) -> Result<HttpResponse> {
+        let timeout = opts.metadata_timeout.unwrap_or(self.metadata_timeout);
+        let mut req = self.client.head(url).timeout(timeout);
+        for (name, value) in &opts.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        let resp = req.send().await.map_err(reqwest_error)?;
+        self.check_redirect(&resp)?;
+
+        // `Response::content_length` reports the (empty) body of a HEAD
+        // response, so read the header directly.
+        let headers = reqwest_headers(&resp);
+        let content_length = headers
+            .iter()
+            .find(|(name, _)| name == "content-length")
+            .and_then(|(_, v)| v.parse::<u64>().ok());
+        let mut out = HttpResponse::new(
+            Vec::new(),
+            resp.status().as_u16(),
             content_length,
-            content_range,
-            retry_after_secs,
+            None,
+            None,
+        );
+        out.headers = headers;
+        out.http_version = Some(format!("{:?}", resp.version()));
+        out.effective_url = Some(resp.url().to_string());
+        Ok(out)
+    }
+
+    async fn get_range_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        opts: &RequestOptions,
+    ) -> Result<HttpResponse> {
+        let (resp, mut out) = self.send_range(url, start, end, opts).await?;
+        if out.status != 416 {
+            let span = end.saturating_sub(start).saturating_add(1);
+            let hint = resp.content_length().unwrap_or(0).min(span);
+            let mut data = Vec::with_capacity(hint as usize);
+            let timeout = self.read_timeout(opts);
+            self.read_range_body(resp, &mut out, start, timeout, |chunk| {
+                data.extend_from_slice(chunk);
+                Ok(())
+            })
+            .await?;
+            out.data = data;
+        }
+        Ok(out)
+    }
+
+    async fn get_range_into_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        buf: &mut [u8],
+        opts: &RequestOptions,
+    ) -> Result<usize> {
+        let (resp, mut out) = self.send_range(url, start, end, opts).await?;
+        if out.status == 416 {
+            return Ok(0);
+        }
+        let mut written = 0;
+        let timeout = self.read_timeout(opts);
+        self.read_range_body(resp, &mut out, start, timeout, |chunk| {
+            let dst = buf
+                .get_mut(written..written + chunk.len())
+                .ok_or_else(body_overflow)?;
+            dst.copy_from_slice(chunk);
+            written += chunk.len();
+            Ok(())
         })
+        .await?;
+        check_body_complete(&out, written, start, end)?;
+        Ok(written)
+    }
+
+    async fn get_stream_with(
+        &self,
+        url: &str,
+        opts: &RequestOptions,
+        sink: BodySink,
+    ) -> Result<()> {
+        let req = self.get_or_post(url, opts);
+        let timeout = self.read_timeout(opts);
+        let mut resp = tokio::time::timeout(timeout, req.send())
+            .await
+            .map_err(|_| FsError::Network("Timed out waiting for response headers".into()))?
+            .map_err(reqwest_error)?;
+        self.check_redirect(&resp)?;
+        let retry_after_secs = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        validate_status(resp.status().as_u16(), retry_after_secs)?;
+
+        let mut received = 0;
+        while let Some(chunk) = self.next_chunk(&mut resp, received, timeout).await? {
+            received += chunk.as_ref().len();
+            if sink.send(chunk.as_ref().to_vec()).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn put_with(&self, url: &str, body: Bytes, opts: &RequestOptions) -> Result<()> {
+        let mut req = self.client.put(url).timeout(self.upload_timeout);
+        for (name, value) in &opts.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        let resp = req.body(body).send().await.map_err(reqwest_error)?;
+        self.check_redirect(&resp)?;
+        let retry_after_secs = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        validate_status(resp.status().as_u16(), retry_after_secs)
     }
 }
 
 #[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+#[derive(Clone)]
 pub(crate) struct CurlAsyncTransport {
     connect_timeout: std::time::Duration,
     read_timeout: std::time::Duration,
+    metadata_timeout: std::time::Duration,
+    upload_timeout: std::time::Duration,
+    ip_family: IpFamily,
+    follow_redirects: bool,
+    receive_buffer_size: Option<usize>,
+    proxy: Option<ProxySpec>,
+    user_agent: String,
+    disable_expect_continue: bool,
+    reject_html: bool,
+    range_unit: String,
+    /// Queue of the multi-handle thread running range GETs, when
+    /// `prefetch_multi` is set, or why that thread could not be started.
+    multi: Option<Result<MultiQueue>>,
 }
 
 #[cfg(all(not(feature = "reqwest"), feature = "curl"))]
@@ -203,100 +1044,705 @@ impl CurlAsyncTransport {
         Self {
             connect_timeout: config.connect_timeout,
             read_timeout: config.read_timeout,
+            metadata_timeout: config.metadata_timeout,
+            upload_timeout: config.upload_timeout,
+            ip_family: config.ip_family,
+            follow_redirects: config.follow_redirects,
+            receive_buffer_size: config.receive_buffer_size,
+            // An invalid proxy is skipped here; opening any file reports it
+            // instead, so no request goes out without the proxy.
+            proxy: config
+                .proxy
+                .as_deref()
+                .and_then(|p| ProxySpec::parse(p).ok()),
+            user_agent: config.user_agent.clone(),
+            disable_expect_continue: config.disable_expect_continue,
+            reject_html: config.reject_html,
+            range_unit: config.range_unit.clone(),
+            multi: config.prefetch_multi.then(spawn_multi_driver),
+        }
+    }
+
+    /// `read_timeout`, unless `opts` overrides it.
+    fn read_timeout(&self, opts: &RequestOptions) -> std::time::Duration {
+        opts.read_timeout.unwrap_or(self.read_timeout)
+    }
+
+    /// An `Easy` handle with the settings shared by every request.
+    fn new_easy(
+        &self,
+        url: &str,
+        timeout: std::time::Duration,
+        opts: &RequestOptions,
+    ) -> Result<curl::easy::Easy> {
+        let mut easy = curl::easy::Easy::new();
+        easy.url(url).map_err(curl_error)?;
+        easy.connect_timeout(self.connect_timeout)
+            .map_err(curl_error)?;
+        easy.timeout(timeout).map_err(curl_error)?;
+        easy.follow_location(self.follow_redirects)
+            .map_err(curl_error)?;
+        easy.useragent(&self.user_agent).map_err(curl_error)?;
+        if let Some(size) = self.receive_buffer_size {
+            easy.buffer_size(size).map_err(curl_error)?;
+        }
+        let ip_resolve = match self.ip_family {
+            IpFamily::Auto => curl::easy::IpResolve::Any,
+            IpFamily::V4Only => curl::easy::IpResolve::V4,
+            IpFamily::V6Only => curl::easy::IpResolve::V6,
+        };
+        easy.ip_resolve(ip_resolve).map_err(curl_error)?;
+        if let Some(proxy) = &self.proxy {
+            easy.proxy(&proxy.url).map_err(curl_error)?;
+            if proxy.socks {
+                easy.proxy_type(curl::easy::ProxyType::Socks5Hostname)
+                    .map_err(curl_error)?;
+            }
+        }
+        if !opts.headers.is_empty() || self.disable_expect_continue {
+            easy.http_headers(self.header_list(&opts.headers)?)
+                .map_err(curl_error)?;
+        }
+        Ok(easy)
+    }
+
+    /// Fail a redirect response if redirects are not being followed.
+    fn check_redirect(&self, status: u16, head: &ResponseHead) -> Result<()> {
+        if self.follow_redirects {
+            return Ok(());
+        }
+        reject_redirect(status, head.headers.get("location").map(String::as_str))
+    }
+
+    /// `headers` as a curl header list.  With `disable_expect_continue`, an
+    /// empty `Expect:` stops curl from adding its own.
+    fn header_list(&self, headers: &[(String, String)]) -> Result<curl::easy::List> {
+        let mut list = curl::easy::List::new();
+        for (name, value) in headers {
+            list.append(&format!("{name}: {value}"))
+                .map_err(curl_error)?;
+        }
+        if self.disable_expect_continue {
+            list.append("Expect:").map_err(curl_error)?;
         }
+        Ok(list)
+    }
+
+    /// Request `range` of the resource, as a POST of `body` if there is one.
+    /// libcurl sends its range option as a `Content-Range` on a POST, and
+    /// only ever in bytes, so a POST or a custom range unit gets an explicit
+    /// `Range` header next to `headers` instead.
+    fn set_range_and_body(
+        &self,
+        easy: &mut curl::easy::Easy,
+        headers: &[(String, String)],
+        range: Option<(u64, u64)>,
+        body: Option<&[u8]>,
+    ) -> Result<()> {
+        let bytes = self.range_unit.eq_ignore_ascii_case("bytes");
+        if let Some(body) = body {
+            easy.post(true).map_err(curl_error)?;
+            easy.post_fields_copy(body).map_err(curl_error)?;
+        } else if bytes {
+            if let Some((s, e)) = range {
+                easy.range(&format!("{s}-{e}")).map_err(curl_error)?;
+            }
+            return Ok(());
+        }
+        if let Some((s, e)) = range {
+            let range = [("Range".to_string(), range_header(&self.range_unit, s, e))];
+            easy.http_headers(self.header_list(&merge_headers(headers, &range))?)
+                .map_err(curl_error)?;
+        }
+        Ok(())
+    }
+
+    fn do_put(&self, url: String, body: Bytes, opts: &RequestOptions) -> Result<u16> {
+        let mut easy = self.new_easy(&url, self.upload_timeout, opts)?;
+        easy.upload(true).map_err(curl_error)?;
+        easy.in_filesize(body.len() as u64).map_err(curl_error)?;
+
+        let mut remaining = &body[..];
+        let mut head = ResponseHead::default();
+        {
+            let mut transfer = easy.transfer();
+            transfer
+                .read_function(|out| {
+                    let n = remaining.len().min(out.len());
+                    out[..n].copy_from_slice(&remaining[..n]);
+                    remaining = &remaining[n..];
+                    Ok(n)
+                })
+                .map_err(curl_error)?;
+            // Discard the response body.
+            transfer
+                .write_function(|chunk| Ok(chunk.len()))
+                .map_err(curl_error)?;
+            transfer
+                .header_function(|header| {
+                    head.observe(header, false);
+                    true
+                })
+                .map_err(curl_error)?;
+            transfer.perform().map_err(curl_error)?;
+        }
+
+        let status = easy.response_code().map_err(curl_error)? as u16;
+        self.check_redirect(status, &head)?;
+        Ok(status)
     }
 
     fn do_request(
+        &self,
         url: String,
         head_only: bool,
         range: Option<(u64, u64)>,
-        connect_timeout: std::time::Duration,
-        read_timeout: std::time::Duration,
+        timeout: std::time::Duration,
+        opts: &RequestOptions,
+        abort: &AtomicBool,
     ) -> Result<HttpResponse> {
-        use ahash::{HashMap, HashMapExt};
+        let data = std::cell::RefCell::new(Vec::new());
+        let mut resp = self.do_request_with(
+            url,
+            head_only,
+            range,
+            timeout,
+            opts,
+            abort,
+            |len| data.borrow_mut().reserve_exact(len),
+            |chunk| {
+                data.borrow_mut().extend_from_slice(chunk);
+                Ok(())
+            },
+        )?;
+        resp.data = data.into_inner();
+        Ok(resp)
+    }
 
-        let mut easy = curl::easy::Easy::new();
-        easy.url(&url)
-            .map_err(|e| FsError::Network(e.to_string()))?;
-        easy.connect_timeout(connect_timeout)
-            .map_err(|e| FsError::Network(e.to_string()))?;
-        easy.timeout(read_timeout)
-            .map_err(|e| FsError::Network(e.to_string()))?;
-        easy.follow_location(true)
-            .map_err(|e| FsError::Network(e.to_string()))?;
+    /// Perform a request, handing the body to `on_data` as it arrives.  The
+    /// returned response has an empty `data`.  Before the first piece,
+    /// `reserve` is told the body length the headers announce, so a buffer
+    /// can be allocated once at its final size.  Setting `abort` stops the
+    /// transfer at curl's next progress callback.
+    #[allow(clippy::too_many_arguments)]
+    fn do_request_with(
+        &self,
+        url: String,
+        head_only: bool,
+        range: Option<(u64, u64)>,
+        timeout: std::time::Duration,
+        opts: &RequestOptions,
+        abort: &AtomicBool,
+        reserve: impl FnOnce(usize),
+        mut on_data: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<HttpResponse> {
+        let mut easy = self.new_easy(&url, timeout, opts)?;
 
         if head_only {
-            easy.nobody(true)
-                .map_err(|e| FsError::Network(e.to_string()))?;
-            easy.custom_request("HEAD")
-                .map_err(|e| FsError::Network(e.to_string()))?;
+            easy.nobody(true).map_err(curl_error)?;
+            easy.custom_request("HEAD").map_err(curl_error)?;
         }
-        if let Some((s, e)) = range {
-            easy.range(&format!("{s}-{e}"))
-                .map_err(|e| FsError::Network(e.to_string()))?;
+        let body = if head_only {
+            None
+        } else {
+            opts.body.as_deref()
+        };
+        self.set_range_and_body(&mut easy, &opts.headers, range, body)?;
+
+        let head = std::cell::RefCell::new(ResponseHead::default());
+        let mut reserve = Some(reserve);
+        let mut sink_error = None;
+        // A multipart/byteranges body is held back and unwrapped once
+        // complete, so `on_data` only ever sees file bytes.
+        let mut framed = Vec::new();
+
+        easy.progress(true).map_err(curl_error)?;
+
+        let performed = {
+            let mut transfer = easy.transfer();
+            transfer
+                .progress_function(|_, _, _, _| !abort.load(Ordering::Relaxed))
+                .map_err(curl_error)?;
+            transfer
+                .write_function(|chunk| {
+                    let mut head = head.borrow_mut();
+                    head.first_byte_at
+                        .get_or_insert_with(std::time::Instant::now);
+                    if let Some(reserve) = reserve.take() {
+                        reserve(head.body_len(range));
+                    }
+                    if head.multipart {
+                        framed.extend_from_slice(chunk);
+                        return Ok(chunk.len());
+                    }
+                    match on_data(chunk) {
+                        Ok(()) => Ok(chunk.len()),
+                        Err(e) => {
+                            sink_error = Some(e);
+                            // A short count makes curl abort the transfer.
+                            Ok(0)
+                        }
+                    }
+                })
+                .map_err(curl_error)?;
+            transfer
+                .header_function(|header| {
+                    head.borrow_mut().observe(header, range.is_some());
+                    true
+                })
+                .map_err(curl_error)?;
+            transfer.perform()
+        };
+        match sink_error {
+            // Unless the status shows the body was not the requested range
+            // anyway (an error page, a full 200 response), in which case the
+            // caller's status check gives the more useful error.
+            Some(e) if matches!(easy.response_code(), Ok(0 | 206) | Err(_)) => return Err(e),
+            Some(_) => {}
+            None => {
+                performed.map_err(curl_error)?;
+            }
         }
 
-        let mut data = Vec::new();
-        let mut headers = HashMap::<String, String>::new();
+        let status = easy.response_code().map_err(curl_error)? as u16;
+        let head = head.into_inner();
+        self.check_redirect(status, &head)?;
+        let mut resp = head.finish(status, range, &self.range_unit, &framed, on_data)?;
+        resp.effective_url = effective_url(&mut easy);
+        Ok(resp)
+    }
 
-        {
+    /// Plain GET whose body is sent to `sink` piece by piece, blocking while
+    /// the reader is behind.  There is no overall timeout, since a stream
+    /// lasts as long as it keeps delivering; it fails once no data arrives
+    /// for `read_timeout`.  Bodies of non-2xx responses, including
+    /// redirects being followed, are discarded.
+    fn do_stream(
+        &self,
+        url: String,
+        opts: &RequestOptions,
+        abort: &AtomicBool,
+        sink: BodySink,
+    ) -> Result<()> {
+        let mut easy = self.new_easy(&url, std::time::Duration::ZERO, opts)?;
+        self.set_range_and_body(&mut easy, &opts.headers, None, opts.body.as_deref())?;
+        easy.low_speed_limit(1).map_err(curl_error)?;
+        easy.low_speed_time(self.read_timeout(opts))
+            .map_err(curl_error)?;
+        easy.progress(true).map_err(curl_error)?;
+
+        let head = std::cell::RefCell::new(ResponseHead::default());
+        let mut reader_gone = false;
+        let performed = {
             let mut transfer = easy.transfer();
+            transfer
+                .progress_function(|_, _, _, _| !abort.load(Ordering::Relaxed))
+                .map_err(curl_error)?;
             transfer
                 .write_function(|chunk| {
-                    data.extend_from_slice(chunk);
+                    if !(200..300).contains(&head.borrow().status) {
+                        return Ok(chunk.len());
+                    }
+                    if sink.blocking_send(chunk.to_vec()).is_err() {
+                        reader_gone = true;
+                        // A short count makes curl abort the transfer.
+                        return Ok(0);
+                    }
                     Ok(chunk.len())
                 })
-                .map_err(|e| FsError::Network(e.to_string()))?;
+                .map_err(curl_error)?;
             transfer
                 .header_function(|header| {
-                    if let Ok(line) = std::str::from_utf8(header) {
-                        let line = line.trim();
-                        if let Some((name, value)) = line.split_once(':') {
-                            headers.insert(name.trim().to_ascii_lowercase(), value.trim().into());
-                        }
-                    }
+                    head.borrow_mut().observe(header, false);
                     true
                 })
-                .map_err(|e| FsError::Network(e.to_string()))?;
-            transfer
-                .perform()
-                .map_err(|e| FsError::Network(e.to_string()))?;
+                .map_err(curl_error)?;
+            transfer.perform()
+        };
+        if reader_gone {
+            return Ok(());
+        }
+        let head = head.into_inner();
+        if head.status != 0 {
+            self.check_redirect(head.status, &head)?;
+            let retry_after_secs = head
+                .headers
+                .get("retry-after")
+                .and_then(|v| parse_retry_after(v));
+            validate_status(head.status, retry_after_secs)?;
         }
+        performed.map_err(curl_error)
+    }
 
-        let status = easy
-            .response_code()
-            .map_err(|e| FsError::Network(e.to_string()))? as u16;
-        let content_length = headers
+    /// Range GET on the multi-handle thread.  The body is buffered there and
+    /// returned in `data`.
+    async fn multi_range(
+        &self,
+        jobs: &MultiQueue,
+        url: &str,
+        start: u64,
+        end: u64,
+        opts: &RequestOptions,
+    ) -> Result<HttpResponse> {
+        let mut easy = self.new_easy(url, self.read_timeout(opts), opts)?;
+        self.set_range_and_body(
+            &mut easy,
+            &opts.headers,
+            Some((start, end)),
+            opts.body.as_deref(),
+        )?;
+        let collected = Arc::new(std::sync::Mutex::new((ResponseHead::default(), Vec::new())));
+        let abort = AbortOnDrop::new();
+
+        let sink = Arc::clone(&collected);
+        easy.write_function(move |chunk| {
+            if let Ok(mut c) = sink.lock() {
+                let (head, body) = &mut *c;
+                if head.first_byte_at.is_none() {
+                    head.first_byte_at = Some(std::time::Instant::now());
+                    body.reserve_exact(head.body_len(Some((start, end))));
+                }
+                body.extend_from_slice(chunk);
+            }
+            Ok(chunk.len())
+        })
+        .map_err(curl_error)?;
+        let sink = Arc::clone(&collected);
+        easy.header_function(move |header| {
+            if let Ok(mut c) = sink.lock() {
+                c.0.observe(header, true);
+            }
+            true
+        })
+        .map_err(curl_error)?;
+        let flag = Arc::clone(&abort.0);
+        easy.progress(true).map_err(curl_error)?;
+        easy.progress_function(move |_, _, _, _| !flag.load(Ordering::Relaxed))
+            .map_err(curl_error)?;
+
+        let (reply, done) = tokio::sync::oneshot::channel();
+        jobs.send(MultiJob { easy, reply })?;
+        let mut easy = done.await.map_err(|_| multi_stopped())??;
+        let status = easy.response_code().map_err(curl_error)? as u16;
+
+        let (head, body) = std::mem::take(
+            &mut *collected
+                .lock()
+                .map_err(|_| FsError::Io("Response buffer poisoned".into()))?,
+        );
+        self.check_redirect(status, &head)?;
+        let multipart = head.multipart;
+        let mut payload = Vec::new();
+        let mut resp = head.finish(status, Some((start, end)), &self.range_unit, &body, |p| {
+            payload.extend_from_slice(p);
+            Ok(())
+        })?;
+        resp.data = if multipart { payload } else { body };
+        resp.effective_url = effective_url(&mut easy);
+        Ok(resp)
+    }
+}
+
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+fn effective_url(easy: &mut curl::easy::Easy) -> Option<String> {
+    easy.effective_url().ok().flatten().map(str::to_owned)
+}
+
+/// Status line and headers of a curl response, collected as they arrive.
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+#[derive(Default)]
+struct ResponseHead {
+    headers: ahash::HashMap<String, String>,
+    http_version: Option<String>,
+    /// Status of the latest status line, 0 before one arrives.
+    status: u16,
+    /// Whether the body is `multipart/byteranges` (tracked for range
+    /// requests only).
+    multipart: bool,
+    first_byte_at: Option<std::time::Instant>,
+}
+
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+impl ResponseHead {
+    fn observe(&mut self, header: &[u8], ranged: bool) {
+        let Ok(line) = std::str::from_utf8(header) else {
+            return;
+        };
+        let line = line.trim();
+        if let Some(version) = line.split_whitespace().next()
+            && version.starts_with("HTTP/")
+        {
+            // A new status line (e.g. after a redirect) starts a fresh
+            // header block.
+            self.http_version = Some(version.to_string());
+            self.status = line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse().ok())
+                .unwrap_or(0);
+            self.headers.clear();
+            self.multipart = false;
+        } else if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_ascii_lowercase();
+            if ranged && name == "content-type" {
+                self.multipart = multipart_boundary(value.trim()).is_some();
+            }
+            self.headers.insert(name, value.trim().into());
+        }
+    }
+
+    /// Body length announced by `Content-Length`, capped at the requested
+    /// range, which is all a well-behaved server sends; 0 if unknown.
+    fn body_len(&self, range: Option<(u64, u64)>) -> usize {
+        let declared = self
+            .headers
+            .get("content-length")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0);
+        let span = range.map_or(u64::MAX, |(s, e)| e.saturating_sub(s).saturating_add(1));
+        usize::try_from(declared.min(span)).unwrap_or(usize::MAX)
+    }
+
+    /// Build the response, with an empty `data`.  A multipart body held
+    /// back in `framed` is unwrapped and handed to `on_data`.
+    fn finish(
+        self,
+        status: u16,
+        range: Option<(u64, u64)>,
+        unit: &str,
+        framed: &[u8],
+        mut on_data: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<HttpResponse> {
+        let headers = self.headers;
+        let mut content_length = headers
             .get("content-length")
             .and_then(|v| v.parse::<u64>().ok());
-        let content_range = headers
+        let mut content_range = headers
             .get("content-range")
-            .and_then(|v| parse_content_range(v));
+            .and_then(|v| parse_content_range(v, unit));
+        if self.multipart
+            && status == 206
+            && let Some((start, _)) = range
+            && let Some(boundary) = headers
+                .get("content-type")
+                .and_then(|v| multipart_boundary(v))
+        {
+            let (payload, span) = parse_byteranges(framed, boundary, start, unit)?;
+            on_data(&payload)?;
+            content_length = Some(payload.len() as u64);
+            content_range = Some(span);
+        }
         let retry_after_secs = headers
             .get("retry-after")
             .and_then(|v| parse_retry_after(v));
 
         Ok(HttpResponse {
-            data,
+            data: Vec::new(),
             status,
             content_length,
             content_range,
             retry_after_secs,
+            headers: headers.into_iter().collect(),
+            http_version: self.http_version,
+            first_byte_at: self.first_byte_at,
+            effective_url: None,
         })
     }
 }
 
+/// A transfer handed to the multi-handle thread, which replies with the
+/// finished handle once it completes.
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+struct MultiJob {
+    easy: curl::easy::Easy,
+    reply: tokio::sync::oneshot::Sender<Result<curl::easy::Easy>>,
+}
+
+/// Sends transfers to the multi-handle thread, waking it if it is waiting
+/// on the ones it already has.  `None` tells the thread to stop.
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+#[derive(Clone)]
+struct MultiQueue {
+    jobs: std::sync::mpsc::Sender<Option<MultiJob>>,
+    waker: Arc<curl::multi::MultiWaker>,
+    thread: Arc<std::sync::Mutex<Option<std::thread::JoinHandle<()>>>>,
+}
+
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+impl MultiQueue {
+    fn send(&self, job: MultiJob) -> Result<()> {
+        self.jobs.send(Some(job)).map_err(|_| multi_stopped())?;
+        self.waker.wakeup().map_err(|_| multi_stopped())
+    }
+
+    /// Abort the thread's transfers and wait for it to exit.
+    fn stop(&self) {
+        if self.jobs.send(None).is_ok() {
+            let _ = self.waker.wakeup();
+        }
+        let thread = self.thread.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+fn multi_stopped() -> FsError {
+    FsError::Network("curl multi thread has stopped".into())
+}
+
+/// Start the thread that drives every queued transfer through one curl
+/// multi handle, so concurrent range GETs share its connection cache (and,
+/// over HTTP/2, a single multiplexed connection) instead of each taking a
+/// blocking thread and connection of its own.  It exits once the transport
+/// and its last transfer are gone, or at once when stopped.
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+fn spawn_multi_driver() -> Result<MultiQueue> {
+    use std::sync::mpsc::TryRecvError;
+
+    let (jobs, queue) = std::sync::mpsc::channel::<Option<MultiJob>>();
+    let (ready, started) = std::sync::mpsc::sync_channel(1);
+    let thread = std::thread::Builder::new()
+        .name("pravaha-multi".into())
+        .spawn(move || {
+            let mut multi = curl::multi::Multi::new();
+            let _ = multi.pipelining(false, true);
+            if ready.send(multi.waker()).is_err() {
+                return;
+            }
+            let mut active: ahash::HashMap<usize, (_, tokio::sync::oneshot::Sender<_>)> =
+                ahash::HashMap::default();
+            let mut next_token = 0usize;
+            let mut open = true;
+            loop {
+                // Block for work only when nothing is in flight.
+                let mut incoming = Vec::new();
+                if active.is_empty() {
+                    match queue.recv() {
+                        Ok(job) => incoming.push(job),
+                        Err(_) => return,
+                    }
+                }
+                while open {
+                    match queue.try_recv() {
+                        Ok(job) => incoming.push(job),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => open = false,
+                    }
+                }
+                if incoming.iter().any(Option::is_none) {
+                    for (_, (handle, reply)) in active.drain() {
+                        // Removing a transfer from the handle aborts it.
+                        let _ = multi.remove(handle);
+                        let _ = reply.send(Err(multi_stopped()));
+                    }
+                    for MultiJob { reply, .. } in incoming.into_iter().flatten() {
+                        let _ = reply.send(Err(multi_stopped()));
+                    }
+                    return;
+                }
+                for MultiJob { easy, reply } in incoming.into_iter().flatten() {
+                    let added = multi
+                        .add(easy)
+                        .map_err(|e| e.to_string())
+                        .and_then(|mut h| {
+                            h.set_token(next_token).map_err(|e| e.to_string())?;
+                            Ok(h)
+                        });
+                    match added {
+                        Ok(handle) => {
+                            active.insert(next_token, (handle, reply));
+                            next_token = next_token.wrapping_add(1);
+                        }
+                        Err(e) => {
+                            let _ = reply.send(Err(FsError::Network(e)));
+                        }
+                    }
+                }
+
+                if let Err(e) = multi.perform() {
+                    for (_, (_, reply)) in active.drain() {
+                        let _ = reply.send(Err(FsError::Network(e.to_string())));
+                    }
+                    continue;
+                }
+                let mut finished = Vec::new();
+                multi.messages(|msg| {
+                    if let (Ok(token), Some(result)) = (msg.token(), msg.result()) {
+                        finished.push((token, result));
+                    }
+                });
+                for (token, result) in finished {
+                    let Some((handle, reply)) = active.remove(&token) else {
+                        continue;
+                    };
+                    let finished = match (multi.remove(handle), result) {
+                        (Ok(easy), Ok(())) => Ok(easy),
+                        (Err(e), _) => Err(FsError::Network(e.to_string())),
+                        (_, Err(e)) => Err(curl_error(e)),
+                    };
+                    let _ = reply.send(finished);
+                }
+
+                if !active.is_empty() {
+                    // Returns early when a transfer needs attention or a
+                    // new one is queued.
+                    let _ = multi.poll(&mut [], std::time::Duration::from_secs(1));
+                }
+                if !open && active.is_empty() {
+                    return;
+                }
+            }
+        })
+        .map_err(|e| FsError::Io(format!("Failed to spawn curl multi thread: {e}")))?;
+    let waker = started.recv().map_err(|_| multi_stopped())?;
+    Ok(MultiQueue {
+        jobs,
+        waker: Arc::new(waker),
+        thread: Arc::new(std::sync::Mutex::new(Some(thread))),
+    })
+}
+
+/// Aborts a blocking curl transfer when the future waiting for it is
+/// dropped, e.g. when a prefetch is cancelled.  The blocking thread cannot
+/// be stopped directly, so it would otherwise finish the download.
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+struct AbortOnDrop(Arc<AtomicBool>);
+
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+impl AbortOnDrop {
+    fn new() -> Self {
+        Self(Arc::new(AtomicBool::new(false)))
+    }
+}
+
+#[cfg(all(not(feature = "reqwest"), feature = "curl"))]
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
 #[cfg(all(not(feature = "reqwest"), feature = "curl"))]
 #[async_trait::async_trait]
 impl AsyncHttp for CurlAsyncTransport {
     async fn get_content_length(&self, url: &str) -> Result<Option<u64>> {
-        let url = url.to_string();
-        let ct = self.connect_timeout;
-        let rt = self.read_timeout;
-        let resp = tokio::task::spawn_blocking(move || Self::do_request(url, true, None, ct, rt))
+        self.get_content_length_with(url, &RequestOptions::default())
             .await
-            .map_err(|e| FsError::Network(e.to_string()))??;
+    }
 
+    async fn get_range(&self, url: &str, start: u64, end: u64) -> Result<HttpResponse> {
+        self.get_range_with(url, start, end, &RequestOptions::default())
+            .await
+    }
+
+    async fn get_content_length_with(
+        &self,
+        url: &str,
+        opts: &RequestOptions,
+    ) -> Result<Option<u64>> {
+        let resp = self.head_with(url, opts).await?;
         if (200..300).contains(&resp.status) {
             Ok(resp.content_length)
         } else {
@@ -304,16 +1750,52 @@ impl AsyncHttp for CurlAsyncTransport {
         }
     }
 
-    async fn get_range(&self, url: &str, start: u64, end: u64) -> Result<HttpResponse> {
+    async fn head_with(&self, url: &str, opts: &RequestOptions) -> Result<HttpResponse> {
         let url = url.to_string();
-        let ct = self.connect_timeout;
-        let rt = self.read_timeout;
-        let resp = tokio::task::spawn_blocking(move || {
-            Self::do_request(url, false, Some((start, end)), ct, rt)
+        let this = self.clone();
+        let opts = opts.clone();
+        let abort = AbortOnDrop::new();
+        let flag = Arc::clone(&abort.0);
+        tokio::task::spawn_blocking(move || {
+            let timeout = opts.metadata_timeout.unwrap_or(this.metadata_timeout);
+            this.do_request(url, true, None, timeout, &opts, &flag)
         })
         .await
-        .map_err(|e| FsError::Network(e.to_string()))??;
+        .map_err(|e| FsError::Network(e.to_string()))?
+    }
+
+    async fn get_range_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        opts: &RequestOptions,
+    ) -> Result<HttpResponse> {
+        let resp = match &self.multi {
+            Some(Ok(jobs)) => self.multi_range(jobs, url, start, end, opts).await?,
+            Some(Err(e)) => return Err(e.clone()),
+            None => {
+                let url = url.to_string();
+                let this = self.clone();
+                let opts = opts.clone();
+                let abort = AbortOnDrop::new();
+                let flag = Arc::clone(&abort.0);
+                tokio::task::spawn_blocking(move || {
+                    this.do_request(
+                        url,
+                        false,
+                        Some((start, end)),
+                        this.read_timeout(&opts),
+                        &opts,
+                        &flag,
+                    )
+                })
+                .await
+                .map_err(|e| FsError::Network(e.to_string()))??
+            }
+        };
 
+        check_not_html(self.reject_html, resp.status, resp.header("content-type"))?;
         validate_range_response(
             resp.status,
             resp.content_range,
@@ -322,4 +1804,398 @@ impl AsyncHttp for CurlAsyncTransport {
         )?;
         Ok(resp)
     }
+
+    /// Runs the transfer on the thread polling this future rather than on
+    /// the blocking pool, since `buf` cannot be moved there.  Only poll it
+    /// where blocking is acceptable (the sync API's `block_on`).
+    async fn get_range_into_with(
+        &self,
+        url: &str,
+        start: u64,
+        end: u64,
+        buf: &mut [u8],
+        opts: &RequestOptions,
+    ) -> Result<usize> {
+        let mut written = 0;
+        let resp = self.do_request_with(
+            url.to_string(),
+            false,
+            Some((start, end)),
+            self.read_timeout(opts),
+            opts,
+            &AtomicBool::new(false),
+            |_| {},
+            |chunk| {
+                let dst = buf
+                    .get_mut(written..written + chunk.len())
+                    .ok_or_else(body_overflow)?;
+                dst.copy_from_slice(chunk);
+                written += chunk.len();
+                Ok(())
+            },
+        )?;
+        check_not_html(self.reject_html, resp.status, resp.header("content-type"))?;
+        validate_range_response(
+            resp.status,
+            resp.content_range,
+            start,
+            resp.retry_after_secs,
+        )?;
+        check_body_complete(&resp, written, start, end)?;
+        Ok(if resp.status == 416 { 0 } else { written })
+    }
+
+    async fn put_with(&self, url: &str, body: Bytes, opts: &RequestOptions) -> Result<()> {
+        let url = url.to_string();
+        let this = self.clone();
+        let opts = opts.clone();
+        let status = tokio::task::spawn_blocking(move || this.do_put(url, body, &opts))
+            .await
+            .map_err(|e| FsError::Network(e.to_string()))??;
+        validate_status(status, None)
+    }
+
+    async fn get_stream_with(
+        &self,
+        url: &str,
+        opts: &RequestOptions,
+        sink: BodySink,
+    ) -> Result<()> {
+        let url = url.to_string();
+        let this = self.clone();
+        let opts = opts.clone();
+        let abort = AbortOnDrop::new();
+        let flag = Arc::clone(&abort.0);
+        tokio::task::spawn_blocking(move || this.do_stream(url, &opts, &flag, sink))
+            .await
+            .map_err(|e| FsError::Network(e.to_string()))?
+    }
+
+    /// Stops the `prefetch_multi` thread, aborting what it still runs.
+    fn shutdown(&self) {
+        if let Some(Ok(queue)) = &self.multi {
+            queue.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod range_tests {
+    use super::*;
+
+    #[test]
+    fn custom_range_unit_is_formatted_and_parsed() {
+        assert_eq!(range_header("samples", 0, 1000), "samples=0-1000");
+        assert_eq!(range_header("bytes", 5, 9), "bytes=5-9");
+
+        let header = "samples 0-1000/44100";
+        assert_eq!(parse_content_range(header, "samples"), Some((0, 1000)));
+        assert_eq!(parse_content_range(header, "Samples"), Some((0, 1000)));
+        assert_eq!(parse_content_range(header, "bytes"), None);
+        assert_eq!(parse_content_range("bytes 0-9/10", "samples"), None);
+    }
+
+    fn multipart(parts: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut body = b"preamble\r\n".to_vec();
+        for (range, data) in parts {
+            body.extend_from_slice(b"--B\r\nContent-Type: application/octet-stream\r\n");
+            body.extend_from_slice(format!("Content-Range: bytes {range}/1000\r\n\r\n").as_bytes());
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--B--\r\n");
+        body
+    }
+
+    #[test]
+    fn byteranges_parts_are_joined_into_one_span() {
+        // Payload bytes that look like framing are only data.
+        let first: &[u8] = b"ab\r\n\r\n--B\r\n";
+        let body = multipart(&[("100-110", first), ("111-113", b"xyz")]);
+        let (payload, span) = parse_byteranges(&body, "B", 100, "bytes").unwrap();
+        assert_eq!(payload, [first, b"xyz"].concat());
+        assert_eq!(span, (100, 113));
+    }
+
+    #[test]
+    fn malformed_byteranges_part_is_a_protocol_error() {
+        let max = format!("{}-{}", u64::MAX, u64::MAX);
+        let cases: [(&str, Vec<u8>); 6] = [
+            ("inverted range", multipart(&[("100-99", b"")])),
+            ("range ending at u64::MAX", multipart(&[(&max, b"a")])),
+            (
+                "gap between parts",
+                multipart(&[("100-101", b"ab"), ("103-103", b"c")]),
+            ),
+            ("truncated part", multipart(&[("100-109", b"abc")])),
+            (
+                "missing Content-Range",
+                b"--B\r\nContent-Type: text/plain\r\n\r\nabc\r\n--B--".to_vec(),
+            ),
+            ("no boundary", b"abc".to_vec()),
+        ];
+        for (what, body) in cases {
+            let start = if what == "range ending at u64::MAX" {
+                u64::MAX
+            } else {
+                100
+            };
+            let err = parse_byteranges(&body, "B", start, "bytes").unwrap_err();
+            assert!(matches!(err, FsError::Protocol(_)), "{what}: {err}");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod reqwest_tests {
+    use super::*;
+
+    #[test]
+    fn post_body_is_shared_not_copied() {
+        let transport = ReqwestAsyncTransport::new(&HttpConfig::default());
+        let opts = RequestOptions::default().with_body(b"{\"id\": 7}".to_vec());
+        let req = transport
+            .get_or_post("http://x/object", &opts)
+            .build()
+            .unwrap();
+
+        assert_eq!(req.method(), reqwest::Method::POST);
+        let sent = req.body().and_then(reqwest::Body::as_bytes).unwrap();
+        assert_eq!(sent.as_ptr(), opts.body.as_ref().unwrap().as_ptr());
+    }
+
+    #[test]
+    fn ip_family_binds_the_local_address() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/a", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                    line.clear();
+                }
+                let reply = "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-9/10\r\n\
+                             Content-Length: 10\r\nConnection: close\r\n\r\n0123456789";
+                let _ = stream.write_all(reply.as_bytes());
+            }
+        });
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let get = |ip_family| {
+            let config = HttpConfig {
+                ip_family,
+                ..HttpConfig::default()
+            };
+            let transport = ReqwestAsyncTransport::new(&config);
+            rt.block_on(transport.get_range(&url, 0, 9))
+        };
+
+        assert_eq!(get(IpFamily::Auto).unwrap().data, b"0123456789");
+        assert_eq!(get(IpFamily::V4Only).unwrap().data, b"0123456789");
+        // An IPv6 socket cannot reach the IPv4 server.
+        assert!(get(IpFamily::V6Only).is_err());
+    }
+}
+
+#[cfg(all(test, not(feature = "reqwest"), feature = "curl"))]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// A keep-alive HTTP/1.1 server on localhost serving 4 KiB of zeros to
+    /// range GETs.  Requests for `/slow` are held until `release` is set.
+    /// Returns its base URL and the number of connections accepted so far.
+    fn serve(release: Arc<AtomicBool>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let count = Arc::clone(&accepted);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                count.fetch_add(1, Ordering::SeqCst);
+                let release = Arc::clone(&release);
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).is_ok_and(|n| n > 0) {
+                        let slow = line.contains("/slow");
+                        let mut range = (0, 0);
+                        loop {
+                            line.clear();
+                            reader.read_line(&mut line).unwrap();
+                            if let Some(v) = line.trim().strip_prefix("Range: bytes=")
+                                && let Some((s, e)) = v.split_once('-')
+                            {
+                                range = (s.parse::<u64>().unwrap(), e.parse::<u64>().unwrap());
+                            }
+                            if line.trim().is_empty() {
+                                break;
+                            }
+                        }
+                        while slow && !release.load(Ordering::SeqCst) {
+                            std::thread::sleep(Duration::from_millis(5));
+                        }
+                        let (s, e) = range;
+                        let head = format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {s}-{e}/4096\r\nContent-Length: {}\r\n\r\n",
+                            e - s + 1
+                        );
+                        stream.write_all(head.as_bytes()).unwrap();
+                        stream.write_all(&vec![0; (e - s + 1) as usize]).unwrap();
+                        line.clear();
+                    }
+                });
+            }
+        });
+        (base, accepted)
+    }
+
+    #[test]
+    fn multi_thread_starts_queued_transfers_at_once_and_reuses_connections() {
+        let release = Arc::new(AtomicBool::new(false));
+        let (base, accepted) = serve(Arc::clone(&release));
+        let config = HttpConfig {
+            prefetch_multi: true,
+            ..HttpConfig::default()
+        };
+        let transport = Arc::new(CurlAsyncTransport::new(&config));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let slow = {
+            let (transport, url) = (Arc::clone(&transport), format!("{base}/slow"));
+            rt.spawn(async move { transport.get_range(&url, 0, 99).await })
+        };
+        std::thread::sleep(Duration::from_millis(100));
+
+        // Queued while the thread waits on the held transfer.
+        let started = Instant::now();
+        let fast = rt.block_on(transport.get_range(&format!("{base}/fast"), 100, 199));
+        assert_eq!(fast.unwrap().data.len(), 100);
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        release.store(true, Ordering::SeqCst);
+        assert_eq!(rt.block_on(slow).unwrap().unwrap().data.len(), 100);
+        for start in [0, 1024, 2048] {
+            let resp = rt.block_on(transport.get_range(&format!("{base}/fast"), start, start + 9));
+            assert_eq!(resp.unwrap().data.len(), 10);
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn shutdown_aborts_the_multi_threads_transfers_and_joins_it() {
+        let release = Arc::new(AtomicBool::new(false));
+        let (base, accepted) = serve(Arc::clone(&release));
+        let config = HttpConfig {
+            prefetch_multi: true,
+            ..HttpConfig::default()
+        };
+        let transport = Arc::new(CurlAsyncTransport::new(&config));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let slow = {
+            let (transport, url) = (Arc::clone(&transport), format!("{base}/slow"));
+            rt.spawn(async move { transport.get_range(&url, 0, 99).await })
+        };
+        while accepted.load(Ordering::SeqCst) == 0 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        // The held transfer fails at once instead of waiting on the server.
+        transport.shutdown();
+        let Some(Ok(queue)) = &transport.multi else {
+            panic!("no multi thread");
+        };
+        assert!(queue.thread.lock().unwrap().is_none());
+        assert!(rt.block_on(slow).unwrap().is_err());
+        let after = rt.block_on(transport.get_range(&format!("{base}/fast"), 0, 9));
+        assert!(after.is_err());
+        release.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn ip_family_restricts_the_addresses_connected_to() {
+        let (base, _) = serve(Arc::new(AtomicBool::new(true)));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let get = |ip_family| {
+            let config = HttpConfig {
+                ip_family,
+                ..HttpConfig::default()
+            };
+            let transport = CurlAsyncTransport::new(&config);
+            let url = base.replace("127.0.0.1", "localhost");
+            rt.block_on(transport.get_range(&format!("{url}/a"), 0, 9))
+        };
+
+        // The server listens on 127.0.0.1 only, which IPv6 never reaches.
+        assert_eq!(get(IpFamily::Auto).unwrap().data.len(), 10);
+        assert_eq!(get(IpFamily::V4Only).unwrap().data.len(), 10);
+        assert!(get(IpFamily::V6Only).is_err());
+    }
+
+    #[test]
+    fn custom_range_unit_goes_out_in_the_range_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/track", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            while reader.read_line(&mut request).unwrap() > 2 {}
+            let reply = "HTTP/1.1 206 Partial Content\r\nContent-Range: samples 0-9/100\r\n\
+                         Content-Length: 10\r\nConnection: close\r\n\r\n0123456789";
+            stream.write_all(reply.as_bytes()).unwrap();
+            request
+        });
+        let config = HttpConfig {
+            range_unit: "samples".into(),
+            ..HttpConfig::default()
+        };
+        let transport = CurlAsyncTransport::new(&config);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let resp = rt.block_on(transport.get_range(&url, 0, 9)).unwrap();
+        assert_eq!(resp.content_range, Some((0, 9)));
+        assert_eq!(resp.data, b"0123456789");
+        let request = server.join().unwrap();
+        assert!(request.contains("\r\nRange: samples=0-9\r\n"), "{request}");
+    }
+
+    #[test]
+    fn curl_errors_are_classified_by_code() {
+        // CURLE_RECV_ERROR, as for a connection reset, and
+        // CURLE_COULDNT_CONNECT are retried; the rest are the request's fault.
+        let classify = |code| curl_error(curl::Error::new(code));
+        assert!(matches!(classify(56), FsError::Network(_)));
+        assert!(matches!(classify(7), FsError::Network(_)));
+        assert!(matches!(classify(3), FsError::InvalidUrl(_)));
+        assert!(matches!(classify(1), FsError::UnsupportedProtocol(_)));
+        assert!(matches!(classify(48), FsError::Io(_)));
+    }
+
+    #[test]
+    fn disabling_expect_continue_sends_an_empty_expect() {
+        let headers = [("X-A".to_string(), "1".to_string())];
+        let list = |disable| {
+            let config = HttpConfig {
+                disable_expect_continue: disable,
+                ..HttpConfig::default()
+            };
+            let list = CurlAsyncTransport::new(&config).header_list(&headers);
+            list.unwrap()
+                .iter()
+                .map(|h| String::from_utf8(h.to_vec()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(list(false), ["X-A: 1"]);
+        assert_eq!(list(true), ["X-A: 1", "Expect:"]);
+    }
 }
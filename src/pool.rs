@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use crate::core::{File, OpenMode, Result};
+use crate::http::{HttpFile, HttpFileSystem};
+
+/// Reuses [`HttpFile`] handles across opens of the same URL, e.g. in a
+/// server that answers many requests for a few files.
+///
+/// [`checkout`](Self::checkout) hands out an idle handle for the URL, rewound
+/// to offset 0, or opens a new one.  The handle returns to the pool when the
+/// [`PooledFile`] is dropped, keeping the chunk it last read and the size it
+/// resolved, so the next checkout can start without a request.  At most
+/// `max_idle_per_url` handles are kept per URL; extra ones are closed.  The
+/// pool is cheap to clone and all clones share the same handles.
+#[derive(Clone)]
+pub struct HttpFilePool {
+    shared: Arc<PoolState>,
+}
+
+struct PoolState {
+    // Declared before `fs` so idle files are closed before its runtime stops.
+    idle: Mutex<HashMap<String, Vec<HttpFile>>>,
+    max_idle_per_url: usize,
+    fs: HttpFileSystem,
+}
+
+impl HttpFilePool {
+    pub fn new(fs: HttpFileSystem, max_idle_per_url: usize) -> Self {
+        Self {
+            shared: Arc::new(PoolState {
+                idle: Mutex::new(HashMap::new()),
+                max_idle_per_url,
+                fs,
+            }),
+        }
+    }
+
+    /// The filesystem new handles are opened on.
+    pub fn fs(&self) -> &HttpFileSystem {
+        &self.shared.fs
+    }
+
+    /// An idle handle for `url` rewound to the start, or a newly opened one.
+    pub fn checkout(&self, url: &str) -> Result<PooledFile> {
+        let idle = self
+            .shared
+            .lock()
+            .get_mut(url)
+            .and_then(|files| files.pop());
+        let file = match idle {
+            Some(mut file) => {
+                file.recycle();
+                file
+            }
+            None => self.shared.fs.open_file(url, OpenMode::Read)?,
+        };
+        Ok(PooledFile {
+            file: Some(file),
+            url: url.to_string(),
+            pool: Arc::clone(&self.shared),
+        })
+    }
+
+    /// How many idle handles are kept for `url`.
+    pub fn idle(&self, url: &str) -> usize {
+        self.shared.lock().get(url).map_or(0, Vec::len)
+    }
+}
+
+impl PoolState {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<HttpFile>>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// An [`HttpFile`] checked out of an [`HttpFilePool`], returned to it on
+/// drop unless it was closed.
+pub struct PooledFile {
+    file: Option<HttpFile>,
+    url: String,
+    pool: Arc<PoolState>,
+}
+
+impl PooledFile {
+    /// Keep the handle instead of returning it to the pool.
+    pub fn into_inner(mut self) -> HttpFile {
+        self.file.take().expect("file is present until dropped")
+    }
+}
+
+impl Deref for PooledFile {
+    type Target = HttpFile;
+
+    fn deref(&self) -> &HttpFile {
+        self.file.as_ref().expect("file is present until dropped")
+    }
+}
+
+impl DerefMut for PooledFile {
+    fn deref_mut(&mut self) -> &mut HttpFile {
+        self.file.as_mut().expect("file is present until dropped")
+    }
+}
+
+impl Drop for PooledFile {
+    fn drop(&mut self) {
+        let Some(file) = self.file.take() else {
+            return;
+        };
+        if file.is_closed() {
+            return;
+        }
+        let mut idle = self.pool.lock();
+        let files = idle.entry(std::mem::take(&mut self.url)).or_default();
+        if files.len() < self.pool.max_idle_per_url {
+            files.push(file);
+        }
+    }
+}
+
+/// Hands out `std::io::Read + Seek` readers for code that opens a reader per
+/// operation, e.g. one per archive or image parse.
+///
+/// Readers come from an [`HttpFilePool`], so a reader for a URL that was
+/// read before reuses the earlier handle: its size is already known and its
+/// last chunk is still held, and other chunks come from the shared cache.
+/// Re-reading a file's header through a new reader then costs no requests.
+#[derive(Clone)]
+pub struct CachedReaderFactory {
+    pool: HttpFilePool,
+}
+
+impl CachedReaderFactory {
+    /// A factory keeping at most `max_idle_per_url` idle handles per URL.
+    pub fn new(fs: HttpFileSystem, max_idle_per_url: usize) -> Self {
+        Self::from(HttpFilePool::new(fs, max_idle_per_url))
+    }
+
+    /// The pool readers are checked out of.
+    pub fn pool(&self) -> &HttpFilePool {
+        &self.pool
+    }
+
+    /// A reader for `url` positioned at offset 0.
+    pub fn reader(&self, url: &str) -> Result<CachedReader> {
+        Ok(CachedReader {
+            file: self.pool.checkout(url)?,
+        })
+    }
+}
+
+impl From<HttpFilePool> for CachedReaderFactory {
+    fn from(pool: HttpFilePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// A `std::io::Read + Seek` reader from a [`CachedReaderFactory`].  Its
+/// handle goes back to the factory's pool on drop.
+pub struct CachedReader {
+    file: PooledFile,
+}
+
+impl CachedReader {
+    /// The pooled handle behind this reader.
+    pub fn into_inner(self) -> PooledFile {
+        self.file
+    }
+}
+
+impl Deref for CachedReader {
+    type Target = HttpFile;
+
+    fn deref(&self) -> &HttpFile {
+        &self.file
+    }
+}
+
+impl Read for CachedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        File::read(&mut *self.file, buf).map_err(io::Error::other)
+    }
+}
+
+impl Seek for CachedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        File::seek_from(&mut *self.file, pos).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::ReadSource;
+    use crate::mock::{Mock, pattern};
+
+    #[test]
+    fn checkout_reuses_the_warm_buffer_of_an_idle_handle() {
+        let mock = Mock::new(pattern(4096));
+        // No shared cache, so only the handle's own buffer can serve reads.
+        let fs = mock.fs().cache_max_entries(0).build();
+        let pool = HttpFilePool::new(fs, 1);
+        let url = "http://x/a";
+
+        let mut file = pool.checkout(url).unwrap();
+        assert_eq!(File::size(&*file), Some(4096));
+        file.read_exact(&mut [0u8; 100]).unwrap();
+        drop(file);
+        assert_eq!(pool.idle(url), 1);
+        let gets = mock.gets();
+
+        let mut file = pool.checkout(url).unwrap();
+        assert_eq!(File::tell(&*file), 0);
+        let mut buf = [0u8; 100];
+        let report = file.read_report(&mut buf).unwrap();
+        assert_eq!(report.source, ReadSource::Buffer);
+        assert_eq!(buf, pattern(100)[..]);
+        assert_eq!(File::size(&*file), Some(4096));
+        assert_eq!(mock.gets(), gets);
+        assert_eq!(file.request_count(), 2);
+
+        // Only `max_idle_per_url` handles are kept.
+        let other = pool.checkout(url).unwrap();
+        drop((file, other));
+        assert_eq!(pool.idle(url), 1);
+    }
+
+    #[test]
+    fn second_reader_reads_the_header_without_a_request() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let data = pattern(10_000);
+        let mock = Mock::new(data.clone());
+        let readers = CachedReaderFactory::new(mock.fs().chunk_size(4096).build(), 2);
+        let url = "http://x/a";
+
+        let mut reader = readers.reader(url).unwrap();
+        let mut header = [0u8; 64];
+        reader.read_exact(&mut header).unwrap();
+        reader.seek(SeekFrom::End(-22)).unwrap();
+        let mut trailer = [0u8; 22];
+        reader.read_exact(&mut trailer).unwrap();
+        assert_eq!(trailer, data[10_000 - 22..]);
+        drop(reader);
+        let requests = (mock.gets(), mock.heads());
+
+        let mut reader = readers.reader(url).unwrap();
+        let mut again = [0u8; 64];
+        reader.read_exact(&mut again).unwrap();
+        assert_eq!(again, header);
+        assert_eq!(again, data[..64]);
+        assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), 10_000);
+        assert_eq!((mock.gets(), mock.heads()), requests);
+    }
+}
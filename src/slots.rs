@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::core::{FsError, Result};
+
+/// Limits how many fetches run at once, like a semaphore, but hands a freed
+/// slot to the waiter with the highest priority rather than the oldest.
+/// Waiters of equal priority are served in arrival order.
+pub(crate) struct FetchSlots {
+    state: Mutex<SlotState>,
+}
+
+struct SlotState {
+    available: usize,
+    waiters: Vec<Waiter>,
+    next_seq: u64,
+}
+
+struct Waiter {
+    seq: u64,
+    /// Read when a slot frees up, so a queued fetch can still be promoted.
+    priority: Arc<AtomicU8>,
+    wake: oneshot::Sender<()>,
+}
+
+impl FetchSlots {
+    pub(crate) fn new(slots: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(SlotState {
+                available: slots,
+                waiters: Vec::new(),
+                next_seq: 0,
+            }),
+        })
+    }
+
+    /// Wait for a slot.  The slot is released when the permit is dropped.
+    pub(crate) async fn acquire(self: &Arc<Self>, priority: Arc<AtomicU8>) -> Result<SlotPermit> {
+        let rx = {
+            let mut state = self.lock();
+            if state.available > 0 {
+                state.available -= 1;
+                return Ok(SlotPermit(Arc::clone(self)));
+            }
+            let (wake, rx) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                seq,
+                priority,
+                wake,
+            });
+            rx
+        };
+        let mut waiting = Waiting {
+            slots: self,
+            rx: Some(rx),
+        };
+        let rx = waiting.rx.as_mut().expect("receiver is set until dropped");
+        rx.await
+            .map_err(|_| FsError::Network("Fetch slots closed".into()))?;
+        waiting.rx = None;
+        Ok(SlotPermit(Arc::clone(self)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SlotState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pass a slot to the best waiter still listening, or free it.
+    fn release(&self) {
+        let mut state = self.lock();
+        while let Some(best) = state
+            .waiters
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, w)| (w.priority.load(Ordering::Relaxed), std::cmp::Reverse(w.seq)))
+            .map(|(i, _)| i)
+        {
+            if state.waiters.swap_remove(best).wake.send(()).is_ok() {
+                return;
+            }
+        }
+        state.available += 1;
+    }
+}
+
+/// A running fetch's slot, released on drop.
+pub(crate) struct SlotPermit(Arc<FetchSlots>);
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// An `acquire` in progress.  If it is cancelled after a slot was already
+/// handed to it, the slot is passed on instead of being lost.
+struct Waiting<'a> {
+    slots: &'a Arc<FetchSlots>,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.slots.release();
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::core::{File, FsError, Result};
+use crate::http::HttpFile;
+
+/// Character encoding of a text file read with [`HttpFile::lines_decoded`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+        }
+    }
+
+    /// Byte length of one code unit; newlines are searched for on these
+    /// boundaries.
+    fn unit(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+        }
+    }
+
+    fn newline(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\n",
+            Encoding::Utf16Le => b"\n\0",
+            Encoding::Utf16Be => b"\0\n",
+        }
+    }
+
+    fn carriage_return(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\r",
+            Encoding::Utf16Le => b"\r\0",
+            Encoding::Utf16Be => b"\0\r",
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<String> {
+        let invalid = || FsError::Io(format!("Line is not valid {self:?}"));
+        let units = |to_u16: fn([u8; 2]) -> u16| {
+            if !bytes.len().is_multiple_of(2) {
+                return Err(invalid());
+            }
+            char::decode_utf16(bytes.chunks_exact(2).map(|c| to_u16([c[0], c[1]])))
+                .collect::<std::result::Result<String, _>>()
+                .map_err(|_| invalid())
+        };
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|_| invalid()),
+            Encoding::Utf16Le => units(u16::from_le_bytes),
+            Encoding::Utf16Be => units(u16::from_be_bytes),
+        }
+    }
+}
+
+/// Iterator returned by [`HttpFile::lines_decoded`].
+pub struct DecodedLines<'a> {
+    file: &'a mut HttpFile,
+    encoding: Encoding,
+    /// Bytes read from the file; those before `consumed` have been returned.
+    pending: Vec<u8>,
+    consumed: usize,
+    /// Whether a leading BOM may still need stripping.
+    at_start: bool,
+    done: bool,
+}
+
+impl DecodedLines<'_> {
+    /// Offset of the first newline in `pending` at or after `from`, which
+    /// must be on a code unit boundary.
+    fn find_newline(&self, from: usize) -> Option<usize> {
+        let (unit, newline) = (self.encoding.unit(), self.encoding.newline());
+        (from..self.pending.len().saturating_sub(unit - 1))
+            .step_by(unit)
+            .find(|&i| &self.pending[i..i + unit] == newline)
+    }
+
+    fn decode_line(&self, line: &[u8]) -> Result<String> {
+        // Like `BufRead::lines`, drop a CR before the LF.
+        let line = line
+            .strip_suffix(self.encoding.carriage_return())
+            .unwrap_or(line);
+        self.encoding.decode(line)
+    }
+}
+
+impl Iterator for DecodedLines<'_> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        let mut searched = self.consumed;
+        loop {
+            if self.at_start {
+                let bom = self.encoding.bom();
+                if self.pending.len() >= bom.len() || self.done {
+                    if self.pending.starts_with(bom) {
+                        self.pending.drain(..bom.len());
+                    }
+                    self.at_start = false;
+                }
+            }
+            if !self.at_start {
+                let start = self.consumed;
+                if let Some(i) = self.find_newline(searched) {
+                    self.consumed = i + self.encoding.unit();
+                    return Some(self.decode_line(&self.pending[start..i]));
+                }
+                if self.done {
+                    if start == self.pending.len() {
+                        return None;
+                    }
+                    self.consumed = self.pending.len();
+                    return Some(self.decode_line(&self.pending[start..]));
+                }
+                // Drop what was returned and resume the search where it
+                // stopped, on a unit boundary.
+                self.pending.drain(..start);
+                self.consumed = 0;
+                let unit = self.encoding.unit();
+                searched = self.pending.len() / unit * unit;
+            }
+
+            let mut buf = [0u8; 64 * 1024];
+            match File::read(self.file, &mut buf) {
+                Ok(0) => self.done = true,
+                Ok(n) => self.pending.extend_from_slice(&buf[..n]),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl HttpFile {
+    /// Iterate over the lines from the current position, decoded from
+    /// `encoding` into `String`s without their line endings (`\n` or
+    /// `\r\n`).  A byte order mark at the start of the file is skipped.
+    /// Characters split across chunk or read boundaries are reassembled; a
+    /// line that is not valid in `encoding` yields an `Io` error.  Reading
+    /// the raw bytes through `std::io::Read` is unaffected.
+    pub fn lines_decoded(&mut self, encoding: Encoding) -> DecodedLines<'_> {
+        let at_start = File::tell(self) == 0;
+        DecodedLines {
+            file: self,
+            encoding,
+            pending: Vec::new(),
+            consumed: 0,
+            at_start,
+            done: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::OpenMode;
+    use crate::mock::Mock;
+
+    fn lines(bytes: Vec<u8>, encoding: Encoding) -> Vec<Result<String>> {
+        let fs = Mock::new(bytes).fs().chunk_size(1000).build();
+        let mut file = fs.open_file("http://x/a.txt", OpenMode::Read).unwrap();
+        file.lines_decoded(encoding).collect()
+    }
+
+    #[test]
+    fn utf8_lines_lose_the_bom_and_keep_characters_split_across_reads() {
+        let mut text = String::new();
+        let mut expected = Vec::new();
+        for i in 0..6000 {
+            let line = format!("{i} héllo €😀");
+            text += &line;
+            text += if i % 2 == 0 { "\r\n" } else { "\n" };
+            expected.push(line);
+        }
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(text.as_bytes());
+        // Characters straddle both the 1000 byte chunks and a 64 KiB read.
+        assert!(std::str::from_utf8(&bytes[3..64 * 1024]).is_err());
+
+        let got: Vec<String> = lines(bytes, Encoding::Utf8)
+            .into_iter()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn utf16_lines_are_decoded_in_either_byte_order() {
+        let text = "a€b\r\n😀x\nlast";
+        for (encoding, mut bytes) in [
+            (Encoding::Utf16Le, vec![0xFF, 0xFE]),
+            (Encoding::Utf16Be, vec![0xFE, 0xFF]),
+        ] {
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&match encoding {
+                    Encoding::Utf16Le => unit.to_le_bytes(),
+                    _ => unit.to_be_bytes(),
+                });
+            }
+            let got: Vec<String> = lines(bytes, encoding)
+                .into_iter()
+                .collect::<Result<_>>()
+                .unwrap();
+            assert_eq!(got, ["a€b", "😀x", "last"]);
+        }
+    }
+
+    #[test]
+    fn invalid_line_is_an_error() {
+        let got = lines(b"ok\n\xff\xfe\nfine".to_vec(), Encoding::Utf8);
+        assert!(
+            matches!(&got[..], [Ok(ok), Err(FsError::Io(_)), Ok(fine)] if ok == "ok" && fine == "fine")
+        );
+    }
+}
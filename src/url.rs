@@ -0,0 +1,409 @@
+use crate::core::{FsError, Result};
+
+/// Returns the scheme of `url` (`"https"` for `https://host/x`), if it has one.
+pub(crate) fn scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once("://")?;
+    let valid = !scheme.is_empty()
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// The decoded path of `url` without its query or fragment.  For an
+/// absolute URL this is everything after the host; a relative path is
+/// returned as is.
+pub(crate) fn path(url: &str) -> String {
+    let path = match url.split_once("://") {
+        Some((_, rest)) if scheme(url).is_some() => rest.find('/').map_or("", |i| &rest[i..]),
+        _ => url,
+    };
+    let end = path.find(['?', '#']).unwrap_or(path.len());
+    percent_decode(&path[..end])
+}
+
+/// When a pre-signed `url` stops being valid, in seconds since the Unix
+/// epoch, read from its query: `X-Amz-Date` plus `X-Amz-Expires` (S3 and
+/// other SigV4 signers), `X-Goog-Date` plus `X-Goog-Expires` (GCS V4), or
+/// an absolute `Expires` (S3 SigV2, GCS V2, CloudFront).  Parameter names
+/// are matched case-insensitively.  `None` if the URL carries no expiry.
+pub(crate) fn signed_url_expiry(url: &str) -> Option<u64> {
+    let url = url.split_once('#').map_or(url, |(u, _)| u);
+    let (_, query) = url.split_once('?')?;
+    let param = |name: &str| {
+        query.split('&').find_map(|p| {
+            let (n, v) = p.split_once('=')?;
+            n.eq_ignore_ascii_case(name).then(|| percent_decode(v))
+        })
+    };
+    for (date, expires) in [
+        ("x-amz-date", "x-amz-expires"),
+        ("x-goog-date", "x-goog-expires"),
+    ] {
+        if let Some(signed) = param(date).as_deref().and_then(parse_basic_datetime)
+            && let Some(valid) = param(expires).and_then(|v| v.parse::<u64>().ok())
+        {
+            return Some(signed.saturating_add(valid));
+        }
+    }
+    param("expires").and_then(|v| v.parse::<u64>().ok())
+}
+
+/// Seconds since the Unix epoch of an ISO 8601 basic UTC timestamp as used
+/// by SigV4, e.g. `20240131T235959Z`.
+fn parse_basic_datetime(s: &str) -> Option<u64> {
+    let digits = |r: std::ops::Range<usize>| s.get(r)?.parse::<u64>().ok();
+    if s.len() != 16 || s.as_bytes()[8] != b'T' || !s.ends_with('Z') {
+        return None;
+    }
+    let (year, month, day) = (digits(0..4)?, digits(4..6)?, digits(6..8)?);
+    let (hour, min, sec) = (digits(9..11)?, digits(11..13)?, digits(13..15)?);
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || min > 59
+        || sec > 60
+    {
+        return None;
+    }
+    // Days from the epoch to the civil date (proleptic Gregorian).
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y % 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+    Some(days * 86_400 + hour * 3600 + min * 60 + sec)
+}
+
+/// Canonicalize an absolute `url` so spellings of the same resource compare
+/// equal: the scheme and host are lowercased, the scheme's default port is
+/// dropped, runs of `/` in the path are collapsed and its `.` and `..`
+/// segments resolved, query parameters are sorted by name (repeated names
+/// keep their order), escapes of unreserved characters are decoded and the
+/// rest get uppercase hex, and the fragment, which is never sent, is
+/// dropped.  The case of the path, query and userinfo is kept.  Relative
+/// paths are returned unchanged.
+pub(crate) fn normalize(url: &str) -> String {
+    let Some(scheme) = scheme(url) else {
+        return url.to_string();
+    };
+    let rest = &url[scheme.len() + 3..];
+    let rest = rest.split_once('#').map_or(rest, |(r, _)| r);
+    let (rest, query) = match rest.split_once('?') {
+        Some((r, q)) => (r, Some(q)),
+        None => (rest, None),
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let (userinfo, host) = match authority.rsplit_once('@') {
+        Some((u, h)) => (Some(u), h),
+        None => (None, authority),
+    };
+
+    let scheme = scheme.to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    let host = match (host.rsplit_once(':'), scheme.as_str()) {
+        (Some((h, "")), _) | (Some((h, "80")), "http") | (Some((h, "443")), "https") => h,
+        _ => &host,
+    };
+
+    let mut out = String::with_capacity(url.len());
+    out.push_str(&scheme);
+    out.push_str("://");
+    if let Some(u) = userinfo {
+        out.push_str(u);
+        out.push('@');
+    }
+    out.push_str(host);
+    let mut collapsed = String::with_capacity(path.len());
+    let mut last_slash = false;
+    for c in normalize_escapes(path).chars() {
+        if !(c == '/' && last_slash) {
+            collapsed.push(c);
+        }
+        last_slash = c == '/';
+    }
+    if !collapsed.is_empty() {
+        out.push_str(&remove_dot_segments(&collapsed));
+    }
+    if let Some(q) = query {
+        let mut params: Vec<String> = q.split('&').map(normalize_escapes).collect();
+        params.sort_by(|a, b| param_name(a).cmp(param_name(b)));
+        out.push('?');
+        out.push_str(&params.join("&"));
+    }
+    out
+}
+
+/// Resolve the `.` and `..` segments of an absolute `path` as RFC 3986
+/// does; a `..` above the root is dropped.
+fn remove_dot_segments(path: &str) -> String {
+    let segments: Vec<&str> = path[1..].split('/').collect();
+    let mut out: Vec<&str> = Vec::with_capacity(segments.len());
+    for (i, segment) in segments.iter().enumerate() {
+        match *segment {
+            "." => {}
+            ".." => {
+                out.pop();
+            }
+            s => {
+                out.push(s);
+                continue;
+            }
+        }
+        // `/a/b/..` names the directory `/a/`.
+        if i + 1 == segments.len() {
+            out.push("");
+        }
+    }
+    format!("/{}", out.join("/"))
+}
+
+fn param_name(param: &str) -> &str {
+    param.split_once('=').map_or(param, |(name, _)| name)
+}
+
+/// Decode `%XX` escapes of unreserved characters and uppercase the hex
+/// digits of the others, leaving everything else as is.
+fn normalize_escapes(s: &str) -> String {
+    let hex = |b: &u8| (*b as char).to_digit(16);
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hi) = bytes.get(i + 1).and_then(hex)
+            && let Some(lo) = bytes.get(i + 2).and_then(hex)
+        {
+            let b = (hi * 16 + lo) as u8;
+            if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+                out.push(b as char);
+            } else {
+                out.push_str(&format!("%{b:02X}"));
+            }
+            i += 3;
+        } else {
+            let len = s[i..].chars().next().map_or(1, char::len_utf8);
+            out.push_str(&s[i..i + len]);
+            i += len;
+        }
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> String {
+    let hex = |b: &u8| (*b as char).to_digit(16);
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hi) = bytes.get(i + 1).and_then(hex)
+            && let Some(lo) = bytes.get(i + 2).and_then(hex)
+        {
+            out.push((hi * 16 + lo) as u8);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Resolve `path` against `base`.
+///
+/// Absolute URLs (anything with a scheme) are returned unchanged.  Relative
+/// paths are appended below `base` -- a leading `/` does *not* escape to the
+/// host root, so a base of `https://host/bucket/` acts as a handle to that
+/// directory.  `..` segments are rejected for the same reason, whether
+/// percent-encoded or not.  Characters that are not valid in a URL path are
+/// percent-encoded; existing `%XX` escapes are kept as-is.
+pub(crate) fn join(base: &str, path: &str) -> Result<String> {
+    if scheme(path).is_some() {
+        return Ok(path.to_string());
+    }
+
+    let (path_part, query) = match path.split_once('?') {
+        Some((p, q)) => (p, Some(q)),
+        None => (path, None),
+    };
+    // Compare decoded segments: `%2e%2e` is `..` to the server, and a
+    // decoded `%2F` may split a segment in two.
+    if percent_decode(path_part).split('/').any(|seg| seg == "..") {
+        return Err(FsError::InvalidUrl(format!(
+            "'..' is not allowed in relative path: {path}"
+        )));
+    }
+
+    let mut out = String::with_capacity(base.len() + path.len() + 1);
+    out.push_str(base.trim_end_matches('/'));
+    out.push('/');
+    percent_encode_into(&mut out, path_part.trim_start_matches('/'));
+    if let Some(q) = query {
+        out.push('?');
+        percent_encode_into(&mut out, q);
+    }
+    Ok(out)
+}
+
+fn percent_encode_into(out: &mut String, s: &str) {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let is_escape = b == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit();
+        if is_escape || is_url_char(b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+        i += 1;
+    }
+}
+
+fn is_url_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric()
+        || matches!(
+            b,
+            b'-' | b'.'
+                | b'_'
+                | b'~'
+                | b'!'
+                | b'$'
+                | b'&'
+                | b'\''
+                | b'('
+                | b')'
+                | b'*'
+                | b'+'
+                | b','
+                | b';'
+                | b'='
+                | b':'
+                | b'@'
+                | b'/'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_canonicalizes_each_part() {
+        for (url, want) in [
+            // Case folding, of the scheme and host only.
+            ("HTTPS://Host.COM/A/b", "https://host.com/A/b"),
+            ("https://User:PW@Host.com/", "https://User:PW@host.com/"),
+            // Default ports.
+            ("http://host:80/a", "http://host/a"),
+            ("https://host:443/a", "https://host/a"),
+            ("https://host:/a", "https://host/a"),
+            ("http://host:443/a", "http://host:443/a"),
+            ("https://host:8443/a", "https://host:8443/a"),
+            ("https://[::1]:443/a", "https://[::1]/a"),
+            // Repeated slashes and dot segments.
+            ("https://host/a//b///c", "https://host/a/b/c"),
+            ("https://host/a/./b/../c", "https://host/a/c"),
+            ("https://host/a/b/..", "https://host/a/"),
+            ("https://host/a/.", "https://host/a/"),
+            ("https://host/../a", "https://host/a"),
+            ("https://host/a/..b/.c", "https://host/a/..b/.c"),
+            ("https://host/a/%2E%2E/b", "https://host/b"),
+            // Percent-encoding.
+            ("https://host/%7Euser/%61", "https://host/~user/a"),
+            ("https://host/a%2fb%3f", "https://host/a%2Fb%3F"),
+            ("https://host/a?q=%7e%2f", "https://host/a?q=~%2F"),
+            // Query order, and the fragment.
+            ("https://host/a?y=2&x=1&y=1", "https://host/a?x=1&y=2&y=1"),
+            ("https://host/a?b=1#frag", "https://host/a?b=1"),
+            ("https://host", "https://host"),
+            ("relative//path", "relative//path"),
+        ] {
+            assert_eq!(normalize(url), want, "{url}");
+        }
+    }
+
+    #[test]
+    fn join_rejects_encoded_parent_segments() {
+        let base = "https://host/bucket/";
+        for path in [
+            "../x",
+            "a/../x",
+            "%2e%2e/x",
+            "a/.%2E/x",
+            "%2E./x",
+            "a%2F..%2Fx",
+        ] {
+            assert!(join(base, path).is_err(), "{path} escaped the base");
+        }
+        assert_eq!(
+            join(base, "a/..b/c").unwrap(),
+            "https://host/bucket/a/..b/c"
+        );
+        assert_eq!(
+            join(base, "a/%2e/c").unwrap(),
+            "https://host/bucket/a/%2e/c"
+        );
+    }
+
+    #[test]
+    fn join_appends_relative_paths_below_the_base() {
+        for base in ["https://host/bucket", "https://host/bucket/"] {
+            assert_eq!(
+                join(base, "a/b.bin").unwrap(),
+                "https://host/bucket/a/b.bin"
+            );
+            assert_eq!(
+                join(base, "/a/b.bin").unwrap(),
+                "https://host/bucket/a/b.bin"
+            );
+        }
+        assert_eq!(
+            join("https://host/bucket", "my file.txt?v=1 2").unwrap(),
+            "https://host/bucket/my%20file.txt?v=1%202"
+        );
+        assert_eq!(
+            join("https://host/bucket", "a%20b").unwrap(),
+            "https://host/bucket/a%20b"
+        );
+    }
+
+    #[test]
+    fn join_rejects_parent_segments() {
+        let base = "https://host/bucket/";
+        for path in ["..", "../x", "a/../../x", "a/..", "/../x", "a/..?q"] {
+            assert!(
+                matches!(join(base, path), Err(FsError::InvalidUrl(_))),
+                "{path} escaped the base"
+            );
+        }
+        assert_eq!(join(base, "a..b").unwrap(), "https://host/bucket/a..b");
+        assert_eq!(
+            join(base, "a/?q=..").unwrap(),
+            "https://host/bucket/a/?q=.."
+        );
+    }
+
+    #[test]
+    fn join_returns_absolute_urls_in_place_of_the_base() {
+        let base = "https://host/bucket/";
+        for url in [
+            "https://other/x.bin",
+            "http://host/elsewhere/../x",
+            "s3://bucket/key",
+        ] {
+            assert_eq!(join(base, url).unwrap(), url);
+        }
+    }
+}